@@ -0,0 +1,90 @@
+use std::fs;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// goxlr-usb comes from git, so the only place its exact revision is recorded is the lock file,
+// pull it out so it can go in the logs.
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    let version = fs::read_to_string("Cargo.lock")
+        .ok()
+        .and_then(|lock| goxlr_usb_version(&lock))
+        .unwrap_or_else(|| String::from("unknown"));
+    println!("cargo:rustc-env=GOXLR_USB_VERSION={}", version);
+
+    // Which build this is, so it can be identified from --version or a log.
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    let commit = git_commit().unwrap_or_else(|| String::from("unknown"));
+    println!("cargo:rustc-env=BUILD_COMMIT={}", commit);
+    println!("cargo:rustc-env=BUILD_DATE={}", build_date());
+}
+
+// Source tarballs won't have a git checkout, which is fine, the version is still there.
+fn git_commit() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let commit = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!commit.is_empty()).then_some(commit)
+}
+
+// Packagers building reproducibly set SOURCE_DATE_EPOCH, so use it in place of the clock if it's
+// there.
+fn build_date() -> String {
+    let seconds = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|since| since.as_secs())
+        })
+        .unwrap_or(0);
+
+    // Converts days since 1970-01-01 to a calendar date, without pulling in a date crate.
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn goxlr_usb_version(lock: &str) -> Option<String> {
+    let package = lock
+        .split("[[package]]")
+        .find(|package| package.contains("name = \"goxlr-usb\""))?;
+
+    let field = |name: &str| {
+        package
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .map(|value| value.trim().trim_matches('"').to_string())
+    };
+
+    let version = field("version = ")?;
+    let source = field("source = ").unwrap_or_default();
+    match source.rsplit_once('#') {
+        Some((_, commit)) => Some(format!("{} ({})", version, commit)),
+        None => Some(version),
+    }
+}
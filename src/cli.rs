@@ -0,0 +1,252 @@
+use crate::downloader::{check_download_url, download_firmware};
+use crate::firmware::{check_firmware_hashed, VersionChange};
+use crate::goxlr::{Device, GoXLR, UpdateOptions};
+use crate::monitor::monitor_progress;
+use crate::outcome::{exit_code, set_outcome, Outcome};
+use crate::preflight::status_check;
+use crate::{FirmwareDetails, Message, PageMessages, StepMessages};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::thread;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+pub struct CliOptions {
+    pub serial: Option<String>,
+    pub file: Option<PathBuf>,
+    pub download_url: Option<String>,
+    pub allow_downgrade: bool,
+    pub wait_for_startup: bool,
+    pub progress_port: Option<u16>,
+}
+
+// Runs the same steps as the GUI, but prints what's happening rather than drawing it, for anyone
+// updating a machine without a display.
+pub fn run_cli(options: CliOptions) -> ExitCode {
+    if let Err(error) = run(options) {
+        println!("Error: {}", error);
+    }
+    exit_code()
+}
+
+fn run(options: CliOptions) -> Result<(), String> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let mut receiver = monitor_progress(receiver, options.progress_port);
+
+    // Nothing else can be talking to the GoXLR while we update it.
+    let status_sender = sender.clone();
+    thread::spawn(move || status_check(status_sender));
+    wait_for_apps(&mut receiver)?;
+
+    let mut goxlr = GoXLR::new(sender.clone(), options.wait_for_startup);
+    println!("Searching for GoXLR Devices..");
+    goxlr.find_devices(Arc::new(AtomicBool::new(false)));
+    let device = select_device(&mut receiver, &options.serial)?;
+    println!(
+        "Found GoXLR {:?} ({}), hardware revision {}, running firmware {}",
+        device.device_type, device.device_serial, device.hardware_revision, device.version
+    );
+    if device.connection_unstable {
+        println!(
+            "Warning: This GoXLR disconnected while it was being detected, please plug it \
+            directly into a USB port on the back of your computer before updating"
+        );
+    }
+
+    let path = match options.file {
+        Some(path) => path,
+        None => {
+            if let Some(url) = &options.download_url {
+                if let Err(error) = check_download_url(url) {
+                    set_outcome(Outcome::DownloadFailed);
+                    return Err(error);
+                }
+            } else {
+                println!("Downloading the latest firmware..");
+            }
+            let download_sender = sender.clone();
+            let device_type = device.device_type;
+            let url = options.download_url.clone();
+            let cancel = Arc::new(AtomicBool::new(false));
+            thread::spawn(move || download_firmware(download_sender, device_type, url, cancel));
+            wait_for_download(&mut receiver)?
+        }
+    };
+
+    let (firmware, sha256, size) = match check_firmware_hashed(&path) {
+        Ok(checked) => checked,
+        Err(error) => {
+            set_outcome(Outcome::InvalidFirmware);
+            return Err(format!("{}: {}", path.to_string_lossy(), error));
+        }
+    };
+    if firmware.device != device.device_type {
+        set_outcome(Outcome::InvalidFirmware);
+        return Err(format!(
+            "This firmware is for the GoXLR {:?}, not the GoXLR {:?}",
+            firmware.device, device.device_type
+        ));
+    }
+    let change = firmware.version.cmp_to(&device.version);
+    if change != VersionChange::Upgrade && !options.allow_downgrade {
+        set_outcome(Outcome::InvalidFirmware);
+        return Err(format!(
+            "Firmware {} is not newer than {}, use --allow-downgrade to install it anyway",
+            firmware.version, device.version
+        ));
+    }
+
+    println!("{}..", change.describe(device.version, firmware.version));
+    let details = FirmwareDetails {
+        device_type: firmware.device,
+        version: firmware.version,
+        sha256,
+        size,
+        path,
+    };
+    thread::spawn(move || goxlr.do_update(device, details, UpdateOptions::default()));
+    wait_for_update(&mut receiver)
+}
+
+fn wait_for_apps(receiver: &mut UnboundedReceiver<Message>) -> Result<(), String> {
+    let mut last = None;
+    while let Some(message) = receiver.blocking_recv() {
+        if let Message::PageMessage(PageMessages::UpdateStatusCheck(app, beta, util)) = message {
+            if !app && !beta && !util {
+                return Ok(());
+            }
+
+            // This gets sent every second, only mention it when something changes.
+            if last != Some((app, beta, util)) {
+                last = Some((app, beta, util));
+                let running = [
+                    (app, "GoXLR App"),
+                    (beta, "GoXLR Beta App"),
+                    (util, "GoXLR Utility"),
+                ]
+                .iter()
+                .filter(|(running, _)| *running)
+                .map(|(_, name)| *name)
+                .collect::<Vec<_>>()
+                .join(", ");
+                println!("Waiting for the following to close: {}", running);
+            }
+        }
+    }
+    Err(String::from("Status check stopped unexpectedly"))
+}
+
+fn select_device(
+    receiver: &mut UnboundedReceiver<Message>,
+    serial: &Option<String>,
+) -> Result<Device, String> {
+    while let Some(message) = receiver.blocking_recv() {
+        match message {
+            Message::PageMessage(PageMessages::UpdateIgnoredDevices(ignored)) => {
+                for device in ignored {
+                    println!("Ignored {}", device);
+                }
+            }
+            Message::PageMessage(PageMessages::UpdateProblemDevices(problems)) => {
+                for device in problems {
+                    println!("Found but couldn't be read: {}", device);
+                }
+            }
+            Message::PageMessage(PageMessages::UpdateDeviceList(list)) => {
+                let found = match serial {
+                    Some(serial) => list.into_iter().find(|d| &d.device_serial == serial),
+                    None if list.len() > 1 => {
+                        let serials = list
+                            .iter()
+                            .map(|device| device.device_serial.clone())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        return Err(format!(
+                            "Multiple GoXLRs found ({}), please choose one with --device <serial>",
+                            serials
+                        ));
+                    }
+                    None => list.into_iter().next(),
+                };
+
+                return found.ok_or_else(|| {
+                    set_outcome(Outcome::DeviceNotFound);
+                    String::from("No matching GoXLR found")
+                });
+            }
+            _ => {}
+        }
+    }
+    Err(String::from("Device search stopped unexpectedly"))
+}
+
+fn wait_for_download(receiver: &mut UnboundedReceiver<Message>) -> Result<PathBuf, String> {
+    let mut last_percent = 0;
+    let mut last_rate = None;
+    while let Some(message) = receiver.blocking_recv() {
+        match message {
+            Message::PageMessage(PageMessages::DownloadFirmwarePercent(percent)) => {
+                if percent / 10 != last_percent / 10 {
+                    match last_rate {
+                        Some(rate) => println!("  Downloaded {}% ({})", percent, rate),
+                        None => println!("  Downloaded {}%", percent),
+                    }
+                }
+                last_percent = percent;
+            }
+            Message::PageMessage(PageMessages::DownloadFirmwareRate(rate)) => last_rate = rate,
+            Message::PageMessage(PageMessages::DownloadFirmwareSource(url)) => {
+                println!("Downloading from {}", url);
+            }
+            Message::PageMessage(PageMessages::UsingCachedFirmware(info)) => {
+                println!(
+                    "Unable to contact the firmware server, using previously downloaded firmware {}",
+                    info.version
+                );
+            }
+            Message::PageMessage(PageMessages::DownloadFirmwareError(error)) => {
+                set_outcome(Outcome::DownloadFailed);
+                return Err(error);
+            }
+            Message::StepsMessage(StepMessages::SelectFile(Some(path))) => return Ok(path),
+            _ => {}
+        }
+    }
+    Err(String::from("Download stopped unexpectedly"))
+}
+
+fn wait_for_update(receiver: &mut UnboundedReceiver<Message>) -> Result<(), String> {
+    let mut last_percent = 0;
+    let mut last_rate = None;
+    while let Some(message) = receiver.blocking_recv() {
+        match message {
+            Message::PageMessage(PageMessages::UpdateFirmwareStage(stage)) => {
+                println!("{}", stage);
+                last_percent = 0;
+                last_rate = None;
+            }
+            Message::PageMessage(PageMessages::UpdateFirmwarePercent(percent)) => {
+                if percent / 10 != last_percent / 10 {
+                    match last_rate {
+                        Some(rate) => println!("  {}% ({})", percent, rate),
+                        None => println!("  {}%", percent),
+                    }
+                }
+                last_percent = percent;
+            }
+            Message::PageMessage(PageMessages::UpdateFirmwareRate(rate)) => last_rate = rate,
+            Message::PageMessage(PageMessages::UpdateFirmwareRetry(Some(retry))) => {
+                println!("  {}", retry);
+            }
+            Message::PageMessage(PageMessages::UpdateFirmwareMessage(message)) => {
+                println!("{}", message);
+            }
+            Message::StepsMessage(StepMessages::UpdateFinished(outcome)) => set_outcome(outcome),
+            Message::PageMessage(PageMessages::UpdateFirmwareComplete(true)) => return Ok(()),
+            _ => {}
+        }
+    }
+    Err(String::from("Update stopped unexpectedly"))
+}
@@ -0,0 +1,82 @@
+use crate::config::{parse_device, read_config_lines};
+use crate::firmware::VersionNumber;
+use crate::DeviceType;
+use log::warn;
+use std::sync::OnceLock;
+
+// Firmware versions which need a newer GoXLR App to work properly (Device, Firmware, App), these
+// apply to the listed firmware and anything newer.
+const APP_REQUIREMENTS: &[(DeviceType, VersionNumber, &str)] = &[];
+
+// Extra requirements can be added locally, one per line, in the form:
+//   <full|mini|any> <firmware version> <minimum app version>
+const REQUIREMENTS_FILE: &str = "app-requirements.txt";
+
+static REQUIREMENTS: OnceLock<Vec<(Option<DeviceType>, VersionNumber, String)>> = OnceLock::new();
+
+// If the installed App is too old for this firmware, returns the App version needed.
+pub fn required_app_version(
+    device_type: DeviceType,
+    firmware: VersionNumber,
+    installed: &str,
+) -> Option<String> {
+    let requirements = REQUIREMENTS.get_or_init(load_requirements);
+    let installed = parse_app_version(installed)?;
+
+    let required = requirements
+        .iter()
+        .filter(|(device, from, _)| {
+            (device.is_none() || *device == Some(device_type)) && *from <= firmware
+        })
+        .filter_map(|(_, _, app)| parse_app_version(app).map(|parsed| (parsed, app)))
+        .max_by(|(a, _), (b, _)| a.cmp(b))?;
+
+    if installed < required.0 {
+        Some(required.1.clone())
+    } else {
+        None
+    }
+}
+
+// App versions don't always have four parts, so compare however many there are.
+fn parse_app_version(version: &str) -> Option<Vec<u32>> {
+    version
+        .trim()
+        .split('.')
+        .map(|part| part.parse::<u32>().ok())
+        .collect()
+}
+
+fn load_requirements() -> Vec<(Option<DeviceType>, VersionNumber, String)> {
+    let mut requirements: Vec<_> = APP_REQUIREMENTS
+        .iter()
+        .map(|(device, firmware, app)| (Some(*device), *firmware, app.to_string()))
+        .collect();
+
+    for line in read_config_lines(REQUIREMENTS_FILE) {
+        match parse_requirement(&line) {
+            Ok(requirement) => requirements.push(requirement),
+            Err(error) => warn!("Ignoring App Requirement '{}': {}", line, error),
+        }
+    }
+    requirements
+}
+
+fn parse_requirement(line: &str) -> Result<(Option<DeviceType>, VersionNumber, String), String> {
+    let mut parts = line.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(device), Some(firmware), Some(app)) => {
+            if parse_app_version(app).is_none() {
+                return Err(format!("Invalid App Version: {}", app));
+            }
+            Ok((
+                parse_device(device)?,
+                firmware.parse::<VersionNumber>()?,
+                app.to_string(),
+            ))
+        }
+        _ => Err(String::from(
+            "Expected <device> <firmware version> <app version>",
+        )),
+    }
+}
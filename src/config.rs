@@ -0,0 +1,35 @@
+use crate::DeviceType;
+use directories::ProjectDirs;
+use std::fs;
+use std::path::PathBuf;
+
+pub fn config_path(name: &str) -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("", "", "goxlr-firmware-updater")?;
+    Some(dirs.config_dir().join(name))
+}
+
+// Returns the meaningful lines of a config file, skipping blanks and '#' comments. A missing file
+// is the normal case, and just means there's nothing to add.
+pub fn read_config_lines(name: &str) -> Vec<String> {
+    let contents = match config_path(name).map(fs::read_to_string) {
+        Some(Ok(contents)) => contents,
+        _ => return vec![],
+    };
+
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+// 'any' gives None, meaning the line applies to both devices.
+pub fn parse_device(device: &str) -> Result<Option<DeviceType>, String> {
+    match device.to_lowercase().as_str() {
+        "full" => Ok(Some(DeviceType::Full)),
+        "mini" => Ok(Some(DeviceType::Mini)),
+        "any" => Ok(None),
+        _ => Err(format!("Unknown Device: {}", device)),
+    }
+}
@@ -0,0 +1,50 @@
+use directories::ProjectDirs;
+use rfd::{MessageButtons, MessageDialog, MessageLevel};
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        // Keep the normal console output for anyone running from a terminal..
+        default_hook(info);
+
+        let backtrace = Backtrace::force_capture();
+        let report = format!("{}\n\n{}", info, backtrace);
+
+        let description = match write_crash_log(&report) {
+            Some(path) => format!(
+                "The updater has crashed, a crash report has been saved to:\n\n{}",
+                path.to_string_lossy()
+            ),
+            None => format!("The updater has crashed:\n\n{}", info),
+        };
+
+        // Last ditch attempt at letting the user know, this may not work depending on where
+        // (and on what thread) the panic happened.
+        let _ = MessageDialog::new()
+            .set_level(MessageLevel::Error)
+            .set_title("GoXLR Firmware Updater")
+            .set_description(description)
+            .set_buttons(MessageButtons::Ok)
+            .show();
+    }));
+}
+
+fn write_crash_log(report: &str) -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("", "", "goxlr-firmware-updater")?;
+    let crash_dir = dirs.data_dir().join("crashes");
+    fs::create_dir_all(&crash_dir).ok()?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    let path = crash_dir.join(format!("crash-{}.log", timestamp));
+    fs::write(&path, report).ok()?;
+    Some(path)
+}
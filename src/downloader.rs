@@ -1,102 +1,724 @@
+use crate::config::read_config_lines;
+use crate::firmware::{check_firmware, parse_firmware, FirmwareInfo, FIRMWARE_HEADER_SIZE};
+use crate::transfer::TransferRate;
 use crate::{DeviceType, Message, PageMessages, StepMessages};
+use directories::UserDirs;
+use log::{debug, error, info, warn};
 use reqwest::blocking::Client;
-use reqwest::header::RANGE;
+use reqwest::header::{HeaderMap, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
 use reqwest::StatusCode;
 use std::cmp::min;
 use std::fs;
-use std::fs::File;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
 use tokio::sync::mpsc::UnboundedSender;
 
-static CHUNK_SIZE: u64 = 10240;
+// How much of the file to request at a time, this can be overridden (in bytes) with the
+// GOXLR_DOWNLOAD_CHUNK_SIZE environment variable.
+static CHUNK_SIZE: u64 = 512 * 1024;
+static CHUNK_SIZE_VAR: &str = "GOXLR_DOWNLOAD_CHUNK_SIZE";
 
-pub fn download_firmware(sender: UnboundedSender<Message>, device_type: DeviceType) {
-    let full_name = "GoXLR_Firmware.bin";
-    let mini_name = "GoXLR_MINI_Firmware.bin";
+static CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+static REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
 
-    let base_url = "https://mediadl.musictribe.com/media/PLM/sftp/incoming/hybris/import/GOXLR/";
-    let url = match device_type {
-        DeviceType::Full => format!("{}{}", base_url, full_name),
-        DeviceType::Mini => format!("{}{}", base_url, mini_name),
-        DeviceType::Unknown => return,
+static DOWNLOAD_CANCELLED: &str = "Download Cancelled";
+static SERVER_UNREACHABLE: &str = "Unable to contact the firmware server, check your connection";
+
+// Downloads are written here until they're complete, so an interrupted one can be resumed, and
+// can't be mistaken for a usable firmware in the meantime.
+static PARTIAL_EXTENSION: &str = "part";
+
+// Sits next to a partial download, and says which version of the file on the server it came from.
+static VALIDATOR_EXTENSION: &str = "validator";
+
+// Where the firmware can be fetched from, tried in order until one has it.
+static BASE_URLS: &[&str] =
+    &["https://mediadl.musictribe.com/media/PLM/sftp/incoming/hybris/import/GOXLR/"];
+
+// Extra places to try can be added locally, one base URL per line, these are tried first.
+static MIRRORS_FILE: &str = "firmware-mirrors.txt";
+static FULL_NAME: &str = "GoXLR_Firmware.bin";
+static MINI_NAME: &str = "GoXLR_MINI_Firmware.bin";
+
+// A device can enumerate without us recognising what it is, there's no firmware we can fetch for
+// that, so the user needs telling rather than being left staring at an empty progress bar.
+fn firmware_name(device_type: DeviceType) -> Result<&'static str, String> {
+    match device_type {
+        DeviceType::Full => Ok(FULL_NAME),
+        DeviceType::Mini => Ok(MINI_NAME),
+        DeviceType::Unknown => Err(String::from(
+            "Unable to determine the GoXLR's device type, so the correct firmware can't be \
+            downloaded. Please select a firmware file instead.",
+        )),
+    }
+}
+
+// Someone with a direct link to a specific firmware build can download it instead of the latest,
+// it still has to be a valid firmware once it's here, and it has to come over https.
+pub fn check_download_url(url: &str) -> Result<(), String> {
+    let parsed = match reqwest::Url::parse(url.trim()) {
+        Ok(parsed) => parsed,
+        Err(error) => return Err(format!("Invalid URL: {}", error)),
+    };
+    if parsed.scheme() != "https" {
+        return Err(String::from("Only https URLs are supported"));
+    }
+    Ok(())
+}
+
+pub fn check_latest_firmware(
+    sender: UnboundedSender<Message>,
+    device_type: DeviceType,
+    url: Option<String>,
+) {
+    let name = match firmware_name(device_type) {
+        Ok(name) => name,
+        Err(error) => {
+            let message = Message::PageMessage(PageMessages::DownloadFirmwareError(error));
+            let _ = sender.send(message);
+            return;
+        }
+    };
+
+    let result = match &url {
+        Some(url) => check_download_url(url).and_then(|_| fetch_firmware_header(url)),
+        None => try_mirrors(name, fetch_firmware_header).map(|(_, info)| info),
+    };
+    let message = match result {
+        Ok(info) => PageMessages::LatestFirmwareChecked(info),
+
+        // If they've asked for a specific firmware, something else won't do.
+        Err(error)
+            if url.is_none()
+                && error == SERVER_UNREACHABLE
+                && use_cached_firmware(&sender, device_type) =>
+        {
+            return;
+        }
+        Err(error) => {
+            error!("Firmware Version Check Failed: {}", error);
+            PageMessages::DownloadFirmwareError(error)
+        }
+    };
+    let _ = sender.send(Message::PageMessage(message));
+}
+
+fn fetch_firmware_header(url: &str) -> Result<FirmwareInfo, String> {
+    let client = client()?;
+
+    // We only need the header to know what version this is, so just ask for that.
+    let header = format!("bytes=0-{}", FIRMWARE_HEADER_SIZE - 1);
+    let response = match client.get(url).header(RANGE, header).send() {
+        Ok(response) => response,
+        Err(_) => return Err(String::from(SERVER_UNREACHABLE)),
     };
 
-    let output_path = std::env::temp_dir().join(match device_type {
-        DeviceType::Full => full_name,
-        DeviceType::Mini => mini_name,
-        DeviceType::Unknown => "wont_happen",
+    let status = response.status();
+    if !(status == StatusCode::OK || status == StatusCode::PARTIAL_CONTENT) {
+        return Err(String::from("Server rejected the version check"));
+    }
+
+    // If the server ignored the range, we'll be handed the entire file, so stop reading once
+    // we've got the header.
+    let mut data = Vec::with_capacity(FIRMWARE_HEADER_SIZE);
+    if response
+        .take(FIRMWARE_HEADER_SIZE as u64)
+        .read_to_end(&mut data)
+        .is_err()
+    {
+        return Err(String::from("Unable to read the firmware header"));
+    }
+
+    parse_firmware(&data)
+}
+
+pub fn download_firmware(
+    sender: UnboundedSender<Message>,
+    device_type: DeviceType,
+    url: Option<String>,
+    cancel: Arc<AtomicBool>,
+) {
+    let name = match firmware_name(device_type) {
+        Ok(name) => name,
+        Err(error) => {
+            error!("Firmware Download Failed: {}", error);
+            let message = Message::PageMessage(PageMessages::DownloadFirmwareError(error));
+            let _ = sender.send(message);
+            return;
+        }
+    };
+
+    let output_path = download_dir().join(name);
+    let partial_path = partial_path(&output_path);
+
+    // A partial download from the usual servers isn't necessarily the same file, so a specific
+    // URL always starts from scratch, and doesn't leave anything behind for the next one.
+    if url.is_some() {
+        remove_partial(&partial_path);
+    }
+
+    let result = fetch_firmware(&sender, name, &url, &partial_path, &cancel).and_then(|_| {
+        if fs::rename(&partial_path, &output_path).is_err() {
+            return Err(String::from("Unable to name the firmware download"));
+        }
+        remove_partial(&partial_path);
+        versioned_name(&output_path)
     });
+    match result {
+        Ok(output_path) => {
+            // Ok, now we send a file..
+            let message = Message::StepsMessage(StepMessages::SelectFile(Some(output_path)));
+            let _ = sender.send(message);
+        }
+        Err(error)
+            if url.is_none()
+                && error == SERVER_UNREACHABLE
+                && use_cached_firmware(&sender, device_type) => {}
+        Err(error) => {
+            error!("Firmware Download Failed: {}", error);
+
+            // Don't leave a bad file lying around for someone to try and flash later. A partial
+            // one is kept so the next attempt can carry on from it, unless the user gave up.
+            if output_path.exists() && fs::remove_file(&output_path).is_err() {
+                warn!("Unable to remove {}", output_path.to_string_lossy());
+            }
+            if error == DOWNLOAD_CANCELLED || url.is_some() {
+                remove_partial(&partial_path);
+            }
+
+            // The user already knows, they're the one who cancelled it.
+            if error != DOWNLOAD_CANCELLED {
+                let message = Message::PageMessage(PageMessages::DownloadFirmwareError(error));
+                let _ = sender.send(message);
+            }
+        }
+    }
+}
+
+fn fetch_firmware(
+    sender: &UnboundedSender<Message>,
+    name: &str,
+    url: &Option<String>,
+    output_path: &Path,
+    cancel: &AtomicBool,
+) -> Result<(), String> {
+    let client = client()?;
 
-    if output_path.exists() && fs::remove_file(&output_path).is_err() {
-        return;
+    // Find a server which actually has the firmware, and tell the user where it's coming from.
+    let (url, (length, validator)) = match url {
+        Some(url) => {
+            check_download_url(url)?;
+            (url.clone(), firmware_length(&client, url)?)
+        }
+        None => try_mirrors(name, |url| firmware_length(&client, url))?,
+    };
+    let message = Message::PageMessage(PageMessages::DownloadFirmwareSource(url.clone()));
+    let _ = sender.send(message);
+
+    // The firmware on the server can be replaced between attempts, so a previous attempt is only
+    // carried on from if the server still has the same version of the file it came from. If we
+    // can't tell, it's safer to start again.
+    let validator_path = validator_path(output_path);
+    let same_file = validator.is_some() && fs::read_to_string(&validator_path).ok() == validator;
+    if !same_file && output_path.exists() {
+        info!(
+            "Discarding partial download of {}, the server's file may have changed",
+            url
+        );
     }
 
-    let client = Client::new();
+    // Only whole chunks are kept from a previous attempt, the last one may have been
+    // cut off part way through. Anything bigger than the firmware is something else.
+    let chunk_size = chunk_size();
+    let existing = if same_file {
+        fs::metadata(output_path).map(|m| m.len()).unwrap_or(0)
+    } else {
+        0
+    };
+    let resume_from = if existing <= length {
+        existing / chunk_size
+    } else {
+        0
+    };
 
-    // First, download the Manifest, and fetch the filename of the latest version..
-    if let Ok(response) = client.head(&url).send() {
-        println!("{:?}", response);
-        if response.headers().contains_key("content-length") {
-            let length = response
-                .headers()
-                .get("content-length")
-                .and_then(|val| val.to_str().ok())
-                .and_then(|s| s.parse::<u64>().ok());
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(resume_from == 0)
+        .open(output_path)
+        .and_then(|mut file| {
+            file.set_len(resume_from * chunk_size)?;
+            file.seek(SeekFrom::End(0))?;
+            Ok(file)
+        });
+    let mut file = match file {
+        Ok(file) => file,
+        Err(error) => {
+            let dir = output_path.parent().unwrap_or(output_path);
+            warn!(
+                "Unable to create {}: {}",
+                output_path.to_string_lossy(),
+                error
+            );
+            return Err(format!(
+                "Unable to save the firmware to {}, please choose a different \
+                download folder",
+                dir.to_string_lossy()
+            ));
+        }
+    };
 
-            if let Some(length) = length {
-                if length == 0 {
-                    println!("Firmware Length of 0!");
-                    println!("{url}");
-                    return;
-                }
+    // Recorded before anything's downloaded, so whatever we manage to get can be resumed.
+    let recorded = match &validator {
+        Some(validator) => fs::write(&validator_path, validator),
+        None if validator_path.exists() => fs::remove_file(&validator_path),
+        None => Ok(()),
+    };
+    if let Err(error) = recorded {
+        warn!(
+            "Unable to update {}: {}",
+            validator_path.to_string_lossy(),
+            error
+        );
+    }
+
+    let downloaded = resume_from * chunk_size;
+    if downloaded > 0 {
+        info!("Resuming download of {} from {} bytes", url, downloaded);
+    }
+
+    let mut progress = DownloadProgress {
+        sender,
+        cancel,
+        length,
+        downloaded,
+        percentage: 0,
+        rate: TransferRate::resume(length, downloaded),
+    };
+
+    let chunks = if (length % chunk_size) != 0 {
+        length / chunk_size + 1
+    } else {
+        length / chunk_size
+    };
+
+    for i in resume_from..chunks {
+        let start = chunk_size * i;
+        let end = min(((chunk_size * i) + chunk_size) - 1, length);
+
+        if start == end {
+            break;
+        }
 
-                if let Ok(mut file) = File::create(&output_path) {
-                    let mut current_percentage = 0;
-
-                    let chunks = if (length % CHUNK_SIZE) != 0 {
-                        length / CHUNK_SIZE + 1
-                    } else {
-                        length / CHUNK_SIZE
-                    };
-
-                    for i in 0..chunks {
-                        let start = CHUNK_SIZE * i;
-                        let end = min(((CHUNK_SIZE * i) + CHUNK_SIZE) - 1, length);
-
-                        if start == end {
-                            break;
-                        }
-
-                        let header = format!("bytes={}-{}", start, end);
-                        println!("{:?}", header);
-
-                        if let Ok(mut response) = client.get(&url).header(RANGE, header).send() {
-                            let status = response.status();
-                            if !(status == StatusCode::OK || status == StatusCode::PARTIAL_CONTENT)
-                            {
-                                return;
-                            }
-
-                            if std::io::copy(&mut response, &mut file).is_err() {
-                                return;
-                            }
-                            let percentage = ((end as f32 / length as f32) * 100.) as u8;
-                            if percentage != current_percentage {
-                                current_percentage = percentage;
-                                let message = Message::PageMessage(
-                                    PageMessages::DownloadFirmwarePercent(percentage),
-                                );
-                                let _ = sender.send(message);
-                            }
-                        }
-                    }
+        let header = format!("bytes={}-{}", start, end);
+        debug!("{:?}", header);
+
+        // If a chunk fails, say which one, otherwise it just looks like the download stopped.
+        let chunk = format!("chunk {} of {}, from byte {}", i + 1, chunks, start);
+
+        // If the file's changed since we started, the server sends the new one in full instead.
+        let mut request = client.get(&url).header(RANGE, header);
+        if let Some(validator) = &validator {
+            request = request.header(IF_RANGE, validator);
+        }
+
+        let mut response = match request.send() {
+            Ok(response) => response,
+            Err(error) => {
+                warn!("Chunk Request Failed: {}", error);
+                return Err(format!(
+                    "Download failed, check your connection ({})",
+                    chunk
+                ));
+            }
+        };
+
+        // A server which ignores the range (or has a different file) sends the whole file
+        // instead, so throw away anything we've already got, and take it from the start.
+        let status = response.status();
+        if status == StatusCode::OK {
+            warn!("Server sent the whole file instead of a range, downloading all of it");
+            if file.set_len(0).and(file.rewind()).is_err() {
+                return Err(String::from("Unable to write the firmware file"));
+            }
+            progress.downloaded = 0;
+            let copied = progress.copy(&mut response, &mut file);
+            copied.map_err(|error| with_chunk(error, "downloading the whole file"))?;
+            break;
+        }
+        if status != StatusCode::PARTIAL_CONTENT {
+            warn!("Chunk Request Rejected: {:?}", response);
+            return Err(format!(
+                "Server rejected the download ({}, {})",
+                status, chunk
+            ));
+        }
+
+        let copied = progress.copy(&mut response, &mut file);
+        copied.map_err(|error| with_chunk(error, &chunk))?;
+    }
+
+    // A connection dropping between chunks doesn't always show up as an error, so
+    // make sure we actually ended up with the whole thing.
+    let written = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    if progress.downloaded != length || written != length {
+        error!(
+            "Download Incomplete: expected {}, received {}, written {}",
+            length, progress.downloaded, written
+        );
+        return Err(String::from(
+            "The download was incomplete, please try again",
+        ));
+    }
+
+    Ok(())
+}
+
+fn with_chunk(error: String, chunk: &str) -> String {
+    // Cancelling is matched on elsewhere, so that has to stay as it is.
+    if error == DOWNLOAD_CANCELLED {
+        return error;
+    }
+    format!("{} ({})", error, chunk)
+}
+
+// Runs the request against each mirror in turn, until one of them works. We only say the server
+// can't be reached if none of them could be.
+fn try_mirrors<T>(
+    name: &str,
+    mut request: impl FnMut(&str) -> Result<T, String>,
+) -> Result<(String, T), String> {
+    let mut failure = String::from(SERVER_UNREACHABLE);
+    for base in base_urls() {
+        let url = format!("{}{}", base, name);
+        match request(&url) {
+            Ok(value) => return Ok((url, value)),
+            Err(error) => {
+                warn!("Unable to use {}: {}", base, error);
+                if error != SERVER_UNREACHABLE {
+                    failure = error;
                 }
             }
         }
-    } else {
-        return;
     }
+    Err(failure)
+}
+
+fn base_urls() -> Vec<String> {
+    let mut urls = vec![];
+    for line in read_config_lines(MIRRORS_FILE) {
+        // Firmware is flashed straight onto the device, so don't fetch it over plain http.
+        if !line.starts_with("https://") {
+            warn!("Ignoring mirror {}, only https is supported", line);
+            continue;
+        }
+        urls.push(if line.ends_with('/') {
+            line
+        } else {
+            format!("{}/", line)
+        });
+    }
+    urls.extend(BASE_URLS.iter().map(|url| url.to_string()));
+    urls
+}
+
+// Asks the server how big the firmware is, which also tells us whether it's got it at all. Along
+// with that comes whatever identifies this version of the file, if the server gives us one.
+fn firmware_length(client: &Client, url: &str) -> Result<(u64, Option<String>), String> {
+    let response = match client.head(url).send() {
+        Ok(response) => response,
+        Err(_) => return Err(String::from(SERVER_UNREACHABLE)),
+    };
+    debug!("{:?}", response);
+    if !response.status().is_success() {
+        return Err(format!(
+            "Server rejected the download ({})",
+            response.status()
+        ));
+    }
+
+    let length = response
+        .headers()
+        .get("content-length")
+        .and_then(|val| val.to_str().ok())
+        .and_then(|s| s.parse::<u64>().ok());
+
+    match length {
+        Some(0) => {
+            error!("Firmware Length of 0 for {}", url);
+            Err(String::from("Server reported an empty firmware file"))
+        }
+        Some(length) => Ok((length, file_validator(response.headers()))),
+        None => Err(String::from("Unable to determine the firmware size")),
+    }
+}
+
+// When we can't reach the server, the newest firmware we've previously downloaded for this device
+// is better than nothing, returns false if there isn't one.
+fn use_cached_firmware(sender: &UnboundedSender<Message>, device_type: DeviceType) -> bool {
+    let cached = stored_firmware()
+        .into_iter()
+        .find(|(_, info, _)| info.device == device_type);
 
-    // Ok, now we send a file..
-    let message = Message::StepsMessage(StepMessages::SelectFile(Some(output_path)));
+    let (path, info) = match cached {
+        Some((path, info, _)) => (path, info),
+        None => return false,
+    };
+
+    warn!(
+        "Firmware server unreachable, using {}",
+        path.to_string_lossy()
+    );
+    let message = Message::PageMessage(PageMessages::UsingCachedFirmware(info));
+    let _ = sender.send(message);
+    let message = Message::StepsMessage(StepMessages::SelectFile(Some(path)));
     let _ = sender.send(message);
+    true
+}
+
+// We don't know the version until the file's been fetched, so once it's here, move it somewhere
+// which says what it is (eg, GoXLR_Firmware_1.5.9.0.bin), so different versions don't clobber
+// each other.
+fn versioned_name(path: &Path) -> Result<PathBuf, String> {
+    let info = check_firmware(path.to_path_buf())?;
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("GoXLR_Firmware");
+    let versioned = path.with_file_name(format!("{}_{}.bin", stem, info.version));
+
+    if versioned.exists() && fs::remove_file(&versioned).is_err() {
+        return Err(String::from(
+            "Unable to replace the existing firmware download",
+        ));
+    }
+    if fs::rename(path, &versioned).is_err() {
+        return Err(String::from("Unable to name the firmware download"));
+    }
+    Ok(versioned)
+}
+
+// Without these, a dead connection leaves the download sat at the same percentage forever.
+fn client() -> Result<Client, String> {
+    Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|error| format!("Unable to create the download client: {}", error))
+}
+
+// Where the user has asked for downloads to go, if they've picked somewhere.
+static CHOSEN_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+static DEFAULT_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn download_dir() -> PathBuf {
+    if let Some(dir) = CHOSEN_DIR.lock().unwrap().clone() {
+        return dir;
+    }
+    DEFAULT_DIR.get_or_init(default_download_dir).clone()
+}
+
+pub fn set_download_dir(dir: PathBuf) {
+    info!("Firmware will be downloaded to {}", dir.to_string_lossy());
+    CHOSEN_DIR.lock().unwrap().replace(dir);
+}
+
+// Temp is the obvious place, but some locked down machines don't let us write there, so fall back
+// to the user's Downloads folder rather than failing.
+fn default_download_dir() -> PathBuf {
+    let temp = std::env::temp_dir();
+    if is_writable(&temp) {
+        return temp;
+    }
+
+    match UserDirs::new().and_then(|dirs| dirs.download_dir().map(Path::to_path_buf)) {
+        Some(downloads) if is_writable(&downloads) => {
+            warn!(
+                "{} isn't writable, downloading to {}",
+                temp.to_string_lossy(),
+                downloads.to_string_lossy()
+            );
+            downloads
+        }
+        _ => temp,
+    }
+}
+
+fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(".goxlr-firmware-updater");
+    let writable = File::create(&probe).is_ok();
+    let _ = fs::remove_file(&probe);
+    writable
+}
+
+// Everything we've downloaded which is still lying around, along with its size on disk.
+pub fn stored_firmware() -> Vec<(PathBuf, FirmwareInfo, u64)> {
+    let mut found = Vec::new();
+
+    let entries = match fs::read_dir(download_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return found,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !is_download(&path) {
+            continue;
+        }
+
+        if let Ok(info) = check_firmware(path.clone()) {
+            let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            found.push((path, info, size));
+        }
+    }
+
+    found.sort_by_key(|(_, info, _)| std::cmp::Reverse(info.version));
+    found
+}
+
+pub fn clear_stored_firmware() -> Result<(), String> {
+    let mut failed = 0;
+    for name in [FULL_NAME, MINI_NAME] {
+        if !remove_partial(&partial_path(&download_dir().join(name))) {
+            failed += 1;
+        }
+    }
+
+    for (path, _, _) in stored_firmware() {
+        if fs::remove_file(&path).is_err() {
+            warn!("Unable to remove {}", path.to_string_lossy());
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("Unable to remove {} firmware file(s)", failed));
+    }
+    Ok(())
+}
+
+pub fn open_download_dir() {
+    let opener = if cfg!(windows) {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+
+    if let Err(error) = Command::new(opener).arg(download_dir()).spawn() {
+        warn!("Unable to open the download folder: {}", error);
+    }
+}
+
+// The temp directory is shared with everything else, so only touch files we'd have created.
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(PARTIAL_EXTENSION);
+    PathBuf::from(name)
+}
+
+fn validator_path(partial: &Path) -> PathBuf {
+    let mut name = partial.as_os_str().to_owned();
+    name.push(".");
+    name.push(VALIDATOR_EXTENSION);
+    PathBuf::from(name)
+}
+
+// Removes a partial download along with its validator, returns false if either is left behind.
+fn remove_partial(partial: &Path) -> bool {
+    let mut removed = true;
+    for path in [partial.to_path_buf(), validator_path(partial)] {
+        if path.exists() && fs::remove_file(&path).is_err() {
+            warn!("Unable to remove {}", path.to_string_lossy());
+            removed = false;
+        }
+    }
+    removed
+}
+
+// If-Range only works with a strong ETag, so a weak one's no use to us, Last-Modified is the
+// fallback for servers which don't send one.
+fn file_validator(headers: &HeaderMap) -> Option<String> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    match header(ETAG) {
+        Some(etag) if !etag.starts_with("W/") => Some(etag.to_string()),
+        _ => header(LAST_MODIFIED).map(String::from),
+    }
+}
+
+fn is_download(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+
+    let stems = [FULL_NAME, MINI_NAME].map(|name| name.trim_end_matches(".bin"));
+    path.is_file() && name.ends_with(".bin") && stems.iter().any(|stem| name.starts_with(stem))
+}
+
+fn chunk_size() -> u64 {
+    match std::env::var(CHUNK_SIZE_VAR).map(|value| value.parse::<u64>()) {
+        Ok(Ok(size)) if size > 0 => size,
+        Ok(_) => {
+            warn!("Ignoring invalid {}, using {}", CHUNK_SIZE_VAR, CHUNK_SIZE);
+            CHUNK_SIZE
+        }
+        Err(_) => CHUNK_SIZE,
+    }
+}
+
+// Chunks can be fairly large, so progress is reported as the data arrives rather than once per
+// chunk.
+struct DownloadProgress<'a> {
+    sender: &'a UnboundedSender<Message>,
+    cancel: &'a AtomicBool,
+    length: u64,
+    downloaded: u64,
+    percentage: u8,
+    rate: TransferRate,
+}
+
+impl DownloadProgress<'_> {
+    fn copy(&mut self, source: &mut impl Read, file: &mut File) -> Result<(), String> {
+        let mut buffer = [0_u8; 8192];
+        loop {
+            if self.cancel.load(Ordering::Relaxed) {
+                return Err(String::from(DOWNLOAD_CANCELLED));
+            }
+
+            let read = match source.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(read) => read,
+                Err(error) => {
+                    warn!("Chunk Read Failed: {}", error);
+                    return Err(String::from("Download failed, check your connection"));
+                }
+            };
+
+            if file.write_all(&buffer[..read]).is_err() {
+                return Err(String::from("Unable to write the firmware file"));
+            }
+
+            self.downloaded += read as u64;
+            let percentage = ((self.downloaded as f32 / self.length as f32) * 100.) as u8;
+            if percentage != self.percentage {
+                self.percentage = percentage;
+                let message = Message::PageMessage(PageMessages::DownloadFirmwarePercent(
+                    percentage.min(100),
+                ));
+                let _ = self.sender.send(message);
+
+                let rate = self.rate.update(self.downloaded);
+                let message = Message::PageMessage(PageMessages::DownloadFirmwareRate(rate));
+                let _ = self.sender.send(message);
+            }
+        }
+    }
 }
@@ -1,9 +1,30 @@
 use crate::DeviceType;
 use byteorder::{LittleEndian, ReadBytesExt};
+use log::{debug, warn};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
 use std::fmt::Formatter;
+use std::fs;
+use std::fs::File;
 use std::io;
-use std::io::Cursor;
-use std::path::PathBuf;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+// Everything we currently care about lives in the first 64 bytes of the file.
+pub const FIRMWARE_HEADER_SIZE: usize = 64;
+
+const NOT_FIRMWARE: &str = "Not a recognized GoXLR firmware image";
+
+// The name at the start of the header, a NUL terminated string in a 16 byte field.
+const NAME_FIELD_SIZE: usize = 16;
+const FULL_MAGIC: &[u8] = b"GoXLR Firmware";
+const MINI_MAGIC: &[u8] = b"GoXLR-Mini";
+
+// Anything claiming to be bigger than this isn't a length, so we don't treat it as one.
+const MAX_DECLARED_LENGTH: u32 = 16 * 1024 * 1024;
 
 #[derive(Debug, Clone)]
 pub struct FirmwareInfo {
@@ -11,41 +32,208 @@ pub struct FirmwareInfo {
     pub version: VersionNumber,
 }
 
+// Everything we can pull out of the firmware header. We don't know what the rest of the header
+// contains yet, so the raw bytes are kept for anyone wanting to dig into it.
+#[derive(Debug, Clone)]
+pub struct FirmwareHeader {
+    pub name: String,
+    pub device: DeviceType,
+    pub version: VersionNumber,
+    pub length_field: Option<u32>,
+    pub raw: Vec<u8>,
+}
+
+impl FirmwareHeader {
+    pub fn info(&self) -> FirmwareInfo {
+        FirmwareInfo {
+            device: self.device,
+            version: self.version,
+        }
+    }
+}
+
 pub fn check_firmware(path: PathBuf) -> Result<FirmwareInfo, String> {
     load_firmware_file(&path)
 }
 
+// Finds all the firmware files in a directory which are suitable for the device, newest first.
+pub fn find_firmware_files(dir: &Path, device_type: DeviceType) -> Vec<(PathBuf, FirmwareInfo)> {
+    let mut found = Vec::new();
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return found,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_bin = path
+            .extension()
+            .map(|extension| extension.eq_ignore_ascii_case("bin"))
+            .unwrap_or(false);
+
+        if !is_bin || !path.is_file() {
+            continue;
+        }
+
+        if let Ok(header) = load_firmware_header(&path) {
+            if header.device == device_type {
+                found.push((path, header.info()));
+            }
+        }
+    }
+
+    found.sort_by_key(|(_, info)| std::cmp::Reverse(info.version));
+    found
+}
+
+// There's no need to pull in the whole file when all we're after is the name and version.
+pub fn load_firmware_header(file: &Path) -> Result<FirmwareHeader, String> {
+    let mut header = Vec::with_capacity(FIRMWARE_HEADER_SIZE);
+    if let Ok(firmware) = File::open(file) {
+        if firmware
+            .take(FIRMWARE_HEADER_SIZE as u64)
+            .read_to_end(&mut header)
+            .is_err()
+        {
+            return Err(String::from("Unable to read file"));
+        }
+        parse_firmware_header(&header)
+    } else {
+        Err(String::from("Unable to open file"))
+    }
+}
+
 fn load_firmware_file(file: &PathBuf) -> Result<FirmwareInfo, String> {
     if let Ok(firmware) = std::fs::read(file) {
-        // I'm going to assume that if the firmware is < 64 bytes, it doesn't contain the
-        // full firmware header.
-        if firmware.len() < 64 {
-            return Err(String::from("Invalid GoXLR Firmware File"));
+        let header = parse_firmware_header(&firmware)?;
+        check_length_field(&header, firmware.len());
+        Ok(header.info())
+    } else {
+        Err(String::from("Unable to open file"))
+    }
+}
+
+pub fn parse_firmware(firmware: &[u8]) -> Result<FirmwareInfo, String> {
+    parse_firmware_header(firmware).map(|header| header.info())
+}
+
+pub fn parse_firmware_header(firmware: &[u8]) -> Result<FirmwareHeader, String> {
+    // I'm going to assume that if the firmware is < 64 bytes, it doesn't contain the
+    // full firmware header.
+    if firmware.len() < FIRMWARE_HEADER_SIZE {
+        return Err(String::from("Invalid GoXLR Firmware File"));
+    }
+
+    // Is this a Mini, or a full?
+    let device_type = match get_firmware_device(&firmware[0..NAME_FIELD_SIZE]) {
+        Some(device_type) => device_type,
+        None => return Err(String::from(NOT_FIRMWARE)),
+    };
+    let device_name = get_firmware_name(&firmware[0..NAME_FIELD_SIZE]);
+
+    // Next, grab the version for this firmware..
+    let device_version = if let Ok(version) = get_firmware_version(&firmware[24..32]) {
+        version
+    } else {
+        return Err(String::from("Unable to extract firmware version"));
+    };
+
+    // Every real firmware has a version, a blank one means we're looking at something which just
+    // happens to start with the right name.
+    if device_version == VersionNumber(0, 0, 0, 0) {
+        return Err(String::from(NOT_FIRMWARE));
+    }
+
+    Ok(FirmwareHeader {
+        name: device_name,
+        device: device_type,
+        version: device_version,
+        length_field: get_length_field(&firmware[32..36]),
+        raw: firmware[0..FIRMWARE_HEADER_SIZE].to_vec(),
+    })
+}
+
+// The header has no documented length or checksum, so there's nothing we can reliably reject a
+// truncated file on. If the field which looks like a length doesn't match, it's noted in the log,
+// as it may help explain a failed upload.
+fn check_length_field(header: &FirmwareHeader, file_length: usize) {
+    if let Some(length) = header.length_field {
+        if file_length < length as usize {
+            warn!(
+                "Firmware header field suggests {} bytes, but only {} are present",
+                length, file_length
+            );
         }
+    }
+}
 
-        // Is this a Mini, or a full?
-        let device_name = get_firmware_name(&firmware[0..16]);
-        let device_type = if device_name == "GoXLR Firmware" {
-            DeviceType::Full
-        } else if device_name == "GoXLR-Mini" {
-            DeviceType::Mini
-        } else {
-            return Err(String::from("Unknown Device in Firmware Headers"));
-        };
+// Files we've already checked and hashed, along with the size and modified time they had at the
+// time, so selecting the same file again (eg, going back and forth between steps) doesn't mean
+// reading the whole thing again, unless it's changed.
+static CHECKED: Mutex<Vec<(FileStamp, FirmwareInfo, String)>> = Mutex::new(Vec::new());
 
-        // Next, grab the version for this firmware..
-        let device_version = if let Ok(version) = get_firmware_version(&firmware[24..32]) {
-            version
-        } else {
-            return Err(String::from("Unable to extract firmware version"));
-        };
+#[derive(Debug, Clone, PartialEq)]
+struct FileStamp {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
 
-        Ok(FirmwareInfo {
-            device: device_type,
-            version: device_version,
+// The same checks as check_firmware, along with the file's SHA-256 and size.
+pub fn check_firmware_hashed(path: &Path) -> Result<(FirmwareInfo, String, u64), String> {
+    // Taken before the read, so if the file changes part way through, it won't match next time.
+    let stamp = fs::metadata(path).ok().and_then(|metadata| {
+        Some(FileStamp {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            modified: metadata.modified().ok()?,
         })
+    });
+
+    if let Some(stamp) = &stamp {
+        let checked = CHECKED.lock().unwrap();
+        if let Some((_, info, hash)) = checked.iter().find(|(checked, _, _)| checked == stamp) {
+            debug!("Using cached check for {}", path.to_string_lossy());
+            return Ok((info.clone(), hash.clone(), stamp.size));
+        }
+    }
+
+    let firmware = match fs::read(path) {
+        Ok(firmware) => firmware,
+        Err(_) => return Err(String::from("Unable to open file")),
+    };
+    let header = parse_firmware_header(&firmware)?;
+    check_length_field(&header, firmware.len());
+    let info = header.info();
+    let hash = sha256_hex(&firmware);
+    let size = firmware.len() as u64;
+
+    if let Some(stamp) = stamp {
+        let mut checked = CHECKED.lock().unwrap();
+        checked.retain(|(checked, _, _)| checked.path != stamp.path);
+        checked.push((stamp, info.clone(), hash.clone()));
+    }
+    Ok((info, hash, size))
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// The name has to be exactly one we know, and properly terminated, rather than just something
+// which reads the same once any junk has been lossily converted.
+fn get_firmware_device(src: &[u8]) -> Option<DeviceType> {
+    let matches = |magic: &[u8]| src.starts_with(magic) && src.get(magic.len()) == Some(&0x00);
+    if matches(FULL_MAGIC) {
+        Some(DeviceType::Full)
+    } else if matches(MINI_MAGIC) {
+        Some(DeviceType::Mini)
     } else {
-        Err(String::from("Unable to open file"))
+        None
     }
 }
 
@@ -60,9 +248,18 @@ fn get_firmware_name(src: &[u8]) -> String {
     return String::from_utf8_lossy(&src[0..end_index]).to_string();
 }
 
+// Nothing documents this field, it follows the version and has looked like the size of the firmware
+// in the files we've seen, but that's a guess. Anything which can't be a length is ignored.
+fn get_length_field(src: &[u8]) -> Option<u32> {
+    let length = Cursor::new(src).read_u32::<LittleEndian>().ok()?;
+    if length == 0 || length > MAX_DECLARED_LENGTH {
+        return None;
+    }
+    Some(length)
+}
+
 fn get_firmware_version(src: &[u8]) -> Result<VersionNumber, io::Error> {
-    println!("{}", src.len());
-    println!("{:x?}", src);
+    debug!("Firmware Version Bytes: {:x?}", src);
 
     // Unpack the firmware version..
     let mut cursor = Cursor::new(src);
@@ -88,8 +285,216 @@ impl std::fmt::Display for VersionNumber {
     }
 }
 
+// The device's USB release number (bcdDevice), which changes between hardware revisions.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct HardwareRevision(pub u8, pub u8, pub u8);
+
+impl std::fmt::Display for HardwareRevision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+impl VersionNumber {
+    // What flashing this version would do to a device currently running `installed`.
+    pub fn cmp_to(&self, installed: &VersionNumber) -> VersionChange {
+        match self.cmp(installed) {
+            Ordering::Greater => VersionChange::Upgrade,
+            Ordering::Equal => VersionChange::Reinstall,
+            Ordering::Less => VersionChange::Downgrade,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum VersionChange {
+    Upgrade,
+    Reinstall,
+    Downgrade,
+}
+
+impl VersionChange {
+    pub fn label(&self) -> &'static str {
+        match self {
+            VersionChange::Upgrade => "Upgrade",
+            VersionChange::Reinstall => "Reinstall",
+            VersionChange::Downgrade => "Downgrade",
+        }
+    }
+
+    // A reinstall isn't going anywhere, so saying it's 'from A to A' just muddies things.
+    pub fn describe(&self, installed: VersionNumber, selected: VersionNumber) -> String {
+        match self {
+            VersionChange::Upgrade => format!("Upgrading from {} to {}", installed, selected),
+            VersionChange::Reinstall => format!("Reinstalling firmware {}", selected),
+            VersionChange::Downgrade => format!("Downgrading from {} to {}", installed, selected),
+        }
+    }
+}
+
+impl FromStr for VersionNumber {
+    type Err = String;
+
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = version.trim().split('.').collect();
+        if parts.len() != 4 {
+            return Err(format!(
+                "Invalid Version: {} (expected 4 parts, found {})",
+                version,
+                parts.len()
+            ));
+        }
+
+        let mut numbers = [0_u32; 4];
+        for (number, part) in numbers.iter_mut().zip(parts) {
+            *number = match part.parse::<u32>() {
+                Ok(value) => value,
+                Err(_) => {
+                    return Err(format!(
+                        "Invalid Version: {} ('{}' is not a number)",
+                        version, part
+                    ))
+                }
+            };
+        }
+
+        Ok(VersionNumber(
+            numbers[0], numbers[1], numbers[2], numbers[3],
+        ))
+    }
+}
+
 impl std::fmt::Debug for VersionNumber {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}.{}.{}.{}", self.0, self.1, self.2, self.3)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds a header with the name in its 16 byte field, and the packed version and build after
+    // the 8 unidentified bytes.
+    fn header(name: &[u8], packed: u32, build: u32) -> Vec<u8> {
+        let mut firmware = vec![0; FIRMWARE_HEADER_SIZE];
+        firmware[..name.len()].copy_from_slice(name);
+        firmware[24..28].copy_from_slice(&packed.to_le_bytes());
+        firmware[28..32].copy_from_slice(&build.to_le_bytes());
+        firmware
+    }
+
+    #[test]
+    fn rejects_short_firmware() {
+        let firmware = header(b"GoXLR Firmware", 0x1402, 107);
+        assert!(parse_firmware(&firmware[..FIRMWARE_HEADER_SIZE - 1]).is_err());
+        assert!(parse_firmware(&[]).is_err());
+    }
+
+    #[test]
+    fn detects_full_and_mini() {
+        let full = parse_firmware(&header(b"GoXLR Firmware", 0x1402, 107)).unwrap();
+        assert_eq!(full.device, DeviceType::Full);
+
+        let mini = parse_firmware(&header(b"GoXLR-Mini", 0x1402, 107)).unwrap();
+        assert_eq!(mini.device, DeviceType::Mini);
+    }
+
+    #[test]
+    fn unpacks_version() {
+        let firmware = parse_firmware(&header(b"GoXLR Firmware", 0x1402, 107)).unwrap();
+        assert_eq!(firmware.version, VersionNumber(1, 4, 2, 107));
+
+        // The major is everything from bit 12 up, the minor the 4 bits below it, and the patch
+        // the bottom 8, with every bit set none of them should spill into the next.
+        let firmware = parse_firmware(&header(b"GoXLR-Mini", 0x2AFFF, 3)).unwrap();
+        assert_eq!(firmware.version, VersionNumber(0x2A, 0xF, 0xFF, 3));
+    }
+
+    #[test]
+    fn parses_at_exactly_header_size() {
+        let firmware = header(b"GoXLR Firmware", 0x1402, 107);
+        assert_eq!(firmware.len(), FIRMWARE_HEADER_SIZE);
+        assert!(parse_firmware_header(&firmware).is_ok());
+    }
+
+    #[test]
+    fn extracts_header_fields() {
+        let mut firmware = header(b"GoXLR-Mini", 0x1402, 107);
+        firmware[32..36].copy_from_slice(&1_500_000_u32.to_le_bytes());
+        firmware.extend_from_slice(&[0xAB; 16]);
+
+        let header = parse_firmware_header(&firmware).unwrap();
+        assert_eq!(header.name, "GoXLR-Mini");
+        assert_eq!(header.device, DeviceType::Mini);
+        assert_eq!(header.version, VersionNumber(1, 4, 2, 107));
+        assert_eq!(header.length_field, Some(1_500_000));
+        assert_eq!(header.raw, firmware[..FIRMWARE_HEADER_SIZE]);
+    }
+
+    #[test]
+    fn ignores_implausible_length_field() {
+        let mut firmware = header(b"GoXLR Firmware", 0x1402, 107);
+        assert_eq!(parse_firmware_header(&firmware).unwrap().length_field, None);
+
+        firmware[32..36].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(parse_firmware_header(&firmware).unwrap().length_field, None);
+    }
+
+    #[test]
+    fn compares_versions() {
+        let installed = VersionNumber(1, 4, 2, 107);
+        assert_eq!(installed.cmp_to(&installed), VersionChange::Reinstall);
+
+        // The build counts, even when everything else matches.
+        let build = VersionNumber(1, 4, 2, 108);
+        assert_eq!(build.cmp_to(&installed), VersionChange::Upgrade);
+        assert_eq!(installed.cmp_to(&build), VersionChange::Downgrade);
+
+        // Each field outranks everything after it.
+        let newer = [
+            VersionNumber(2, 0, 0, 0),
+            VersionNumber(1, 5, 0, 0),
+            VersionNumber(1, 4, 3, 0),
+        ];
+        for version in newer {
+            assert_eq!(version.cmp_to(&installed), VersionChange::Upgrade);
+            assert_eq!(installed.cmp_to(&version), VersionChange::Downgrade);
+        }
+    }
+
+    #[test]
+    fn describes_reinstall_without_from() {
+        let version = VersionNumber(1, 4, 2, 107);
+        let change = version.cmp_to(&version);
+        assert_eq!(change, VersionChange::Reinstall);
+        assert_eq!(
+            change.describe(version, version),
+            "Reinstalling firmware 1.4.2.107"
+        );
+    }
+
+    #[test]
+    fn identifies_device_from_name() {
+        let field = |name: &[u8]| {
+            let mut field = [0; NAME_FIELD_SIZE];
+            field[..name.len()].copy_from_slice(name);
+            field
+        };
+        assert_eq!(
+            get_firmware_device(&field(b"GoXLR Firmware")),
+            Some(DeviceType::Full)
+        );
+        assert_eq!(
+            get_firmware_device(&field(b"GoXLR-Mini")),
+            Some(DeviceType::Mini)
+        );
+
+        // Anything else, including a known name which carries on, or is never terminated.
+        assert_eq!(get_firmware_device(&field(b"")), None);
+        assert_eq!(get_firmware_device(&field(b"GoXLR")), None);
+        assert_eq!(get_firmware_device(&field(b"GoXLR-Mini2")), None);
+        assert_eq!(get_firmware_device(&[0xFF; NAME_FIELD_SIZE]), None);
+        assert_eq!(get_firmware_device(b"GoXLR-Mini"), None);
+    }
+}
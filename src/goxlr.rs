@@ -1,37 +1,144 @@
-use crate::firmware::VersionNumber;
+use crate::downloader::stored_firmware;
+use crate::firmware::{check_firmware, sha256_hex, HardwareRevision, VersionNumber};
+use crate::locale::{tr, tr_fill};
+use crate::outcome::Outcome;
+use crate::policy::{check_policy, FirmwarePolicy};
+use crate::transfer::TransferRate;
 use crate::PageMessages::{
-    UpdateFirmwareComplete, UpdateFirmwareIsError, UpdateFirmwareMessage, UpdateFirmwarePercent,
-    UpdateFirmwareStage,
+    UpdateFirmwareAwaitingReboot, UpdateFirmwareComplete, UpdateFirmwareDisconnected,
+    UpdateFirmwareIsError, UpdateFirmwareMessage, UpdateFirmwareOverallPercent,
+    UpdateFirmwarePercent, UpdateFirmwareRate, UpdateFirmwareRetry, UpdateFirmwareStage,
+    UpdateFirmwareWriteIncomplete,
 };
-use crate::{DeviceType, FirmwareDetails, Message, PageMessages};
-use goxlr_usb::device::base::FullGoXLRDevice;
+use crate::{DeviceType, FirmwareDetails, Message, PageMessages, StepMessages};
+use directories::ProjectDirs;
+use goxlr_usb::device::base::{FullGoXLRDevice, GoXLRDevice};
 use goxlr_usb::device::{find_devices, from_device};
+use log::{debug, error, info, warn};
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, MutexGuard};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::UnboundedSender;
 
+const DISCONNECTED_MESSAGE: &str = "Your GoXLR was disconnected during the update. Do not unplug \
+    it; reconnect and restart the updater to recover.";
+
+// How much firmware goes in each packet. This is the size the updater has always used, and the
+// GoXLR has always been happy with it. The largest the device will accept isn't documented, so
+// it's the safe choice.
+const UPLOAD_CHUNK_SIZE: usize = 1012;
+
+// Some systems upload faster with bigger packets, this can be set (in bytes) to try it. If the
+// device won't take them, we drop back to the default.
+const UPLOAD_CHUNK_SIZE_VAR: &str = "GOXLR_UPLOAD_CHUNK_SIZE";
+
+// How many times a single firmware chunk is sent before giving up on the update, the delay between
+// attempts grows with each one.
+const UPLOAD_ATTEMPTS: u32 = 3;
+const UPLOAD_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+// How often the NVR erase is polled. The erase takes a few seconds, and only reports its progress
+// in 255 steps, so ten times a second is plenty to keep the progress bar moving, without the polling
+// getting in the way of the erase itself.
+const NVR_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// If the erase hasn't moved on in this long, it's not going to, and we give up on the update
+// rather than sitting on the same percentage forever.
+const NVR_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Verification and the final write report their progress the same way, a short pause between polls
+// keeps us from hammering the USB bus, without noticeably slowing them down.
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+// If a device needs polling more slowly, these can be set (in milliseconds) to override the above.
+const NVR_POLL_INTERVAL_VAR: &str = "GOXLR_NVR_POLL_INTERVAL";
+const STATUS_POLL_INTERVAL_VAR: &str = "GOXLR_STATUS_POLL_INTERVAL";
+
+// The device can report progress far faster than the UI redraws, and every update sits in the
+// message channel until it does. Anything between the start and end of a stage is limited to one
+// update per interval, so the channel never builds up a backlog. 100ms is still smooth to watch.
+// Anything held back goes out on a later poll, so the bar doesn't sit behind the device.
+const PERCENT_INTERVAL: Duration = Duration::from_millis(100);
+
+// On a machine with several GoXLRs, this limits the updater to the one with this serial, so
+// testing (or reproducing a problem) always hits the same device.
+const TARGET_SERIAL_VAR: &str = "GOXLR_TARGET_SERIAL";
+
+// After a reboot, the GoXLR has to start up and play its animation before it'll talk to us again,
+// so give it a good while before deciding it's not coming back.
+const REBOOT_TIMEOUT: Duration = Duration::from_secs(60);
+const REBOOT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
 pub(crate) struct GoXLR {
     sender: UnboundedSender<Message>,
     handles: HashMap<DeviceLocal, Arc<Mutex<Box<dyn FullGoXLRDevice>>>>,
+    stage: Option<UpdateStage>,
+    // When we last sent a percentage, and what it was.
+    percent_sent: Option<(Instant, u8)>,
+    wait_for_startup: bool,
+
+    // Set from the start of an update until the device has been told to reboot, nothing else
+    // should be talking to the device in that time. An update holds the lock on us throughout, so
+    // anything waiting on the lock to check this would only get to it once the update's done. It's
+    // shared, so the UI can check it without the lock, and turn things away rather than queue them.
+    busy: Arc<AtomicBool>,
 }
 
 impl GoXLR {
-    pub(crate) fn new(sender: UnboundedSender<Message>) -> Self {
+    pub(crate) fn new(sender: UnboundedSender<Message>, wait_for_startup: bool) -> Self {
         GoXLR {
             sender,
             handles: HashMap::new(),
+            stage: None,
+            percent_sent: None,
+            wait_for_startup,
+            busy: Arc::new(AtomicBool::new(false)),
         }
     }
 
-    pub fn find_devices(&mut self) {
+    pub fn busy_flag(&self) -> Arc<AtomicBool> {
+        self.busy.clone()
+    }
+
+    fn is_busy(&self) -> bool {
+        self.busy.load(Ordering::Relaxed)
+    }
+
+    fn set_busy(&self, busy: bool) {
+        self.busy.store(busy, Ordering::Relaxed);
+    }
+
+    pub fn find_devices(&mut self, cancel: Arc<AtomicBool>) {
+        if self.is_busy() {
+            info!("Skipping Device Scan, an update is in progress");
+            return;
+        }
+
         let devices = find_devices();
         let mut device_list: Vec<Device> = Vec::new();
 
+        // Anything we couldn't use, and why, so it doesn't just silently vanish from the list.
+        let mut ignored: Vec<String> = Vec::new();
+
+        // GoXLRs which identified themselves, but couldn't be read, the user needs to know about
+        // these as they're most likely the device they're trying to update.
+        let mut problems: Vec<String> = Vec::new();
+        let target = target_serial();
+
         // Create handles for all devices..
         for device in devices {
+            // Opening devices can be slow, so if the user's had enough, hand back what we've got.
+            if cancel.load(Ordering::Relaxed) {
+                info!("Device Scan Cancelled");
+                break;
+            }
+
             let local_device = DeviceLocal {
                 bus_number: device.bus_number(),
                 address: device.address(),
@@ -46,22 +153,16 @@ impl GoXLR {
                     .lock()
                     .unwrap()
             } else {
-                // We don't care about messages being sent out at this point, we're explicitly
-                // going to ignore them and handle errors on-the-fly during the update.
-                let (disconnect_sender, _) = mpsc::channel(32);
-                let (event_sender, _) = mpsc::channel(32);
-
-                // Create the Handle, the pause is only needed if we're waiting for the startup animation to finish, in this
-                // context, we don't care.
-                let handle = from_device(device.clone(), disconnect_sender, event_sender, true);
-                if let Err(error) = &handle {
-                    println!("Error: {}", error);
-                    continue;
-                }
-
-                // Unwrap the Handle, and stop polling for events.
-                let mut handle = handle.unwrap();
-                handle.stop_polling();
+                // A device that's been on for a while is well past its startup animation, so we
+                // only wait for it if we've been asked to.
+                let handle = match open_handle(device.clone(), !self.wait_for_startup) {
+                    Ok(handle) => handle,
+                    Err(reason) => {
+                        warn!("Skipping Device: {}", reason);
+                        ignored.push(describe_ignored(&local_device, &reason));
+                        continue;
+                    }
+                };
 
                 self.handles
                     .insert(local_device.clone(), Arc::new(Mutex::new(handle)));
@@ -72,38 +173,59 @@ impl GoXLR {
                     .unwrap()
             };
 
-            if let Ok(descriptor) = handle.get_descriptor() {
-                let device_type = match descriptor.product_id() {
-                    goxlr_usb::PID_GOXLR_FULL => DeviceType::Full,
-                    goxlr_usb::PID_GOXLR_MINI => DeviceType::Mini,
-                    _ => continue,
-                };
-                if let Ok((device_serial, _)) = handle.get_serial_number() {
-                    if device_serial.is_empty() {
-                        println!("Nope.");
-                        continue;
-                    }
-                    if let Ok(firmware) = handle.get_firmware_version() {
-                        let version = VersionNumber(
-                            firmware.firmware.0,
-                            firmware.firmware.1,
-                            firmware.firmware.2,
-                            firmware.firmware.3,
-                        );
-
-                        device_list.push(Device {
-                            device_type,
-                            device_serial,
-                            version,
-                            goxlr_device: local_device.clone(),
-                        });
+            match read_device_details(&mut handle) {
+                Ok((_, device_serial, _, _))
+                    if target
+                        .as_ref()
+                        .is_some_and(|target| *target != device_serial) =>
+                {
+                    let reason = format!("{} filtered out by {}", device_serial, TARGET_SERIAL_VAR);
+                    ignored.push(describe_ignored(&local_device, &reason));
+                }
+                Ok((device_type, device_serial, version, hardware_revision)) => {
+                    device_list.push(Device {
+                        device_type,
+                        device_serial,
+                        version,
+                        hardware_revision,
+                        goxlr_device: local_device.clone(),
+                        connection_unstable: false,
+                    });
+                }
+                Err(error) => {
+                    warn!("Skipping Device: {}", error);
+                    match handle.get_serial_number() {
+                        Ok((serial, _)) if !serial.is_empty() => {
+                            problems.push(format!("{} ({})", serial, error));
+                        }
+                        _ => ignored.push(describe_ignored(&local_device, &error)),
                     }
                 }
-            } else {
-                println!("Nope!");
             }
         }
-        println!("{:?}", device_list);
+
+        // goxlr-usb doesn't tell us anything about power or the connection, but a device which
+        // drops off the bus while we're talking to it is usually on a struggling hub or cable.
+        let present: Vec<DeviceLocal> = find_devices()
+            .iter()
+            .map(|device| DeviceLocal {
+                bus_number: device.bus_number(),
+                address: device.address(),
+                identifier: device.identifier().clone(),
+            })
+            .collect();
+        for device in device_list.iter_mut() {
+            if !present.contains(&device.goxlr_device) {
+                warn!("{} disconnected during the scan", device.device_serial);
+                device.connection_unstable = true;
+            }
+        }
+        debug!("{:?}", device_list);
+
+        let ignored = PageMessages::UpdateIgnoredDevices(ignored);
+        let _ = self.sender.send(Message::PageMessage(ignored));
+        let problems = PageMessages::UpdateProblemDevices(problems);
+        let _ = self.sender.send(Message::PageMessage(problems));
 
         let _ = self
             .sender
@@ -112,99 +234,468 @@ impl GoXLR {
             )));
     }
 
-    pub fn do_update(&mut self, device: Device, firmware: FirmwareDetails) {
-        // Firstly, pull out the handle, and load the firmware..
-        let handle = self.handles.get_mut(&device.goxlr_device);
-        if handle.is_none() {
-            self.send_setup_error("Unable to retrieve GoXLR from Device");
+    // Performs the same harmless reads as detection, to confirm we can still talk to the device.
+    pub fn test_device(&mut self, device: Device) {
+        let result = match self.handles.get(&device.goxlr_device) {
+            _ if self.is_busy() => Err(String::from("An update is in progress")),
+            Some(handle) => {
+                let arc = handle.clone();
+                let mut handle = arc.lock().unwrap();
+                read_device_details(&mut handle)
+            }
+            None => Err(String::from("Unable to retrieve GoXLR from Device")),
+        };
+
+        let message = match result {
+            Ok((device_type, device_serial, version, revision)) => format!(
+                "Communication OK: GoXLR {:?}, Serial {}, Firmware {}, Hardware {}",
+                device_type, device_serial, version, revision
+            ),
+            Err(error) => format!("Communication Failed: {}", error),
+        };
+
+        let result = PageMessages::DeviceTestResult(message);
+        let _ = self.sender.send(Message::PageMessage(result));
+    }
+
+    // Once the GoXLR has rebooted, waits for it to come back and reports the firmware it's now
+    // running, so we know the update actually took.
+    pub fn check_rebooted_device(&mut self, device: Device) {
+        let result = if self.is_busy() {
+            Err(String::from("An update is in progress"))
+        } else {
+            self.wait_for_device(&device)
+        };
+
+        match &result {
+            Ok(version) => info!("{} is now running {}", device.device_serial, version),
+            Err(error) => warn!("Unable to check {}: {}", device.device_serial, error),
+        }
+        let message = PageMessages::RebootCheckResult(result);
+        let _ = self.sender.send(Message::PageMessage(message));
+    }
+
+    fn wait_for_device(&mut self, device: &Device) -> Result<VersionNumber, String> {
+        // The handle from before the reboot is for a device which has gone away, and the GoXLR
+        // may well come back at a different address.
+        self.handles.remove(&device.goxlr_device);
+
+        let started = Instant::now();
+        while started.elapsed() < REBOOT_TIMEOUT {
+            sleep(REBOOT_POLL_INTERVAL);
+
+            for usb_device in find_devices() {
+                let local_device = DeviceLocal {
+                    bus_number: usb_device.bus_number(),
+                    address: usb_device.address(),
+                    identifier: usb_device.identifier().clone(),
+                };
+                if self.handles.contains_key(&local_device) {
+                    continue;
+                }
+
+                // It's only just rebooted, so it'll still be playing the startup animation.
+                let handle = match open_handle(usb_device, false) {
+                    Ok(handle) => Arc::new(Mutex::new(handle)),
+                    Err(error) => {
+                        debug!("Not ready yet: {}", error);
+                        continue;
+                    }
+                };
+                let details = read_device_details(&mut handle.lock().unwrap());
+                self.handles.insert(local_device, handle);
+
+                if let Ok((_, serial, version, _)) = details {
+                    if serial == device.device_serial {
+                        return Ok(version);
+                    }
+                }
+            }
+        }
+        Err(format!(
+            "The GoXLR didn't come back within {} seconds of rebooting",
+            REBOOT_TIMEOUT.as_secs()
+        ))
+    }
+
+    pub fn do_update(&mut self, device: Device, firmware: FirmwareDetails, options: UpdateOptions) {
+        if self.is_busy() {
+            self.send_setup_error("An update is already in progress");
             return;
         }
+        self.set_busy(true);
+
+        // If the user wants to trigger the reboot themselves, leave the device in update mode.
+        let reboot = !options.pause_before_reboot;
+
+        let result = self.update_device(&device, &firmware, options, reboot);
+
+        // If we're waiting to reboot, the device is still mid-update, so we stay busy.
+        self.set_busy(matches!(result, Ok(()) if !reboot && !options.dry_run));
+        match result {
+            Ok(()) if options.dry_run => self.send_dry_run_complete(&device, &firmware),
+            Ok(()) if reboot => self.send_finish_complete(),
+            Ok(()) => self.send_awaiting_reboot(),
+            Err(error) => {
+                error!("Error: {}", error);
+
+                // The technical error is in the log, the user gets something they can act on. If
+                // we never got as far as starting a stage, this was a setup problem.
+                // The code goes along with it, so a report of the failure says exactly where.
+                match self.stage {
+                    _ if matches!(error, UpdateError::Disconnected(_)) => self
+                        .send_disconnected(&format!("{} ({})", DISCONNECTED_MESSAGE, error.code())),
+                    None => self.send_setup_error(&error.to_string()),
+                    Some(UpdateStage::Finalise) => {
+                        let explanation = UpdateStage::Finalise.explanation();
+                        self.send_write_incomplete(&format!("{} ({})", explanation, error.code()))
+                    }
 
-        // Grab the Handle..
-        let arc = handle.unwrap().clone();
-        let mut handle = arc.lock().unwrap();
+                    // The device hasn't been rebooted, so it's in the same place as a failed write.
+                    Some(stage) if options.stay_in_update_mode_on_failure => {
+                        self.send_write_incomplete(&format!("{} ({})", stage.explanation(), error))
+                    }
+                    Some(stage) => {
+                        self.send_finish_error(&format!("{} ({})", stage.explanation(), error))
+                    }
+                }
+            }
+        }
+    }
 
-        // Grab the Firmware as a byte array..
-        let firmware = if let Ok(firmware) = std::fs::read(firmware.path) {
-            firmware
-        } else {
-            self.send_setup_error("Unable to Load Firmware from Disk");
+    // Updates each device in turn, a failure on one device doesn't stop the rest of the queue,
+    // unless it's left that device with a half written firmware.
+    pub fn do_batch_update(
+        &mut self,
+        devices: Vec<Device>,
+        firmware: FirmwareDetails,
+        options: UpdateOptions,
+    ) {
+        if self.is_busy() {
+            self.send_setup_error("An update is already in progress");
             return;
+        }
+        self.set_busy(true);
+
+        let mut failed = 0;
+        let mut skipped = 0;
+
+        for (index, device) in devices.iter().enumerate() {
+            // Mixed devices can be queued, but only the ones the firmware is for get flashed.
+            if device.device_type != firmware.device_type {
+                let reason = format!("Firmware is not for the GoXLR {:?}", device.device_type);
+                self.send_batch_status(index, BatchStatus::Skipped(reason));
+                skipped += 1;
+                continue;
+            }
+
+            self.send_batch_status(index, BatchStatus::Updating);
+
+            // Pausing before a reboot makes no sense when there's more devices waiting behind it.
+            let status = match self.update_device(device, &firmware, options, true) {
+                Ok(()) => BatchStatus::Complete,
+
+                // A failed write leaves this GoXLR in update mode with a half written firmware,
+                // which needs sorting out before anything else is flashed, so the queue stops.
+                Err(error)
+                    if self.stage == Some(UpdateStage::Finalise)
+                        && !matches!(error, UpdateError::Disconnected(_)) =>
+                {
+                    error!("Error Updating {}: {}", device.device_serial, error);
+                    self.send_batch_status(index, BatchStatus::Failed(error.to_string()));
+                    for remaining in index + 1..devices.len() {
+                        let reason = tr_fill("batch.stopped", &[&device.device_serial]);
+                        self.send_batch_status(remaining, BatchStatus::Skipped(reason));
+                    }
+                    self.set_busy(false);
+
+                    let explanation = UpdateStage::Finalise.explanation();
+                    let serial = &device.device_serial;
+                    let message = format!("{}: {} ({})", serial, explanation, error.code());
+                    self.send_write_incomplete(&message);
+                    return;
+                }
+                Err(error) => {
+                    error!("Error Updating {}: {}", device.device_serial, error);
+                    failed += 1;
+                    BatchStatus::Failed(error.to_string())
+                }
+            };
+            self.send_batch_status(index, status);
+        }
+        self.set_busy(false);
+
+        let succeeded = devices.len() - failed - skipped;
+        self.send_batch_finish(succeeded, failed, skipped, options.dry_run);
+    }
+
+    fn update_device(
+        &mut self,
+        device: &Device,
+        details: &FirmwareDetails,
+        options: UpdateOptions,
+        reboot: bool,
+    ) -> Result<(), UpdateError> {
+        self.stage = None;
+
+        // The UI should have stopped us getting here, but this is the last chance to catch it.
+        match check_policy(details.device_type, details.version) {
+            FirmwarePolicy::Allowed => {}
+            FirmwarePolicy::Blocked(_) if options.allow_blocked_firmware => {}
+            FirmwarePolicy::Blocked(reason) => {
+                return Err(UpdateError::Setup(format!(
+                    "Firmware {} is blocked: {}",
+                    details.version, reason
+                )));
+            }
+            FirmwarePolicy::NotApproved => {
+                let error = format!("Firmware {} is not approved", details.version);
+                return Err(UpdateError::Setup(error));
+            }
+        }
+
+        // Firstly, pull out the handle, and load the firmware..
+        let mut arc = match self.handles.get(&device.goxlr_device) {
+            Some(handle) => handle.clone(),
+            None => {
+                let error = String::from("Unable to retrieve GoXLR from Device");
+                return Err(UpdateError::Setup(error));
+            }
         };
-        let firmware_length = firmware.len() as u32;
+
+        // The handle is looked up by where the device is plugged in, so if it's been swapped for
+        // another GoXLR since it was selected, we'd be flashing the wrong one.
+        if let Err(error) = confirm_serial(&mut arc.lock().unwrap(), &device.device_serial) {
+            return Err(UpdateError::Setup(error));
+        }
+
+        // Grab the Firmware as a byte array..
+        let firmware = match std::fs::read(&details.path) {
+            Ok(firmware) => firmware,
+            Err(_) => {
+                let error = String::from("Unable to Load Firmware from Disk");
+                return Err(UpdateError::Setup(error));
+            }
+        };
+        let local = &device.goxlr_device;
+
+        // The device is given the firmware's length, and validates it, as 32 bit values, so a file
+        // which doesn't fit can't be sent at all. We don't know how much storage the device
+        // actually has, so that's the only size we can check before the NVR is cleared.
+        let firmware_length = match u32::try_from(firmware.len()) {
+            Ok(length) => length,
+            Err(_) => {
+                return Err(UpdateError::Setup(format!(
+                    "Firmware is too large to send to the GoXLR ({} bytes)",
+                    firmware.len()
+                )));
+            }
+        };
+
+        // A dry run goes through everything an update would check, but stops before the device
+        // is touched.
+        if options.dry_run {
+            return check_compatibility(device, details).map_err(UpdateError::Setup);
+        }
+
+        // Show exactly what's about to be flashed, before anything touches the device.
+        let change = details.version.cmp_to(&device.version);
+        let description = change.describe(device.version, details.version);
+        info!("{} on {}", description, device.device_serial);
+        let hash = sha256_hex(&firmware);
+        info!("Flashing {} bytes, SHA-256: {}", firmware.len(), hash);
+        let identity = PageMessages::UpdateFirmwareIdentity(firmware.len() as u64, hash);
+        let _ = self.sender.send(Message::PageMessage(identity));
+
+        // The device can't give us its current firmware back, so the best we can do is note down
+        // what it was running, so the user knows what to go back to if the new one misbehaves.
+        match record_current_firmware(device) {
+            Ok(path) => {
+                info!("Previous firmware recorded to {}", path.to_string_lossy());
+                let record = PageMessages::UpdateFirmwareVersionRecord(path);
+                let _ = self.sender.send(Message::PageMessage(record));
+            }
+            Err(error) => warn!("Unable to record previous firmware: {}", error),
+        }
 
         // Ok, got the device, got the firmware, lets goooooooo..
-        if let Err(e) = handle.begin_firmware_upload() {
-            let error = format!("Failed to put device in Update Mode: {}", e);
-            self.send_setup_error(error.as_str());
-            return;
+        if let Err(e) = self.upload_sequence(local, &mut arc, &firmware, firmware_length) {
+            // The device has gone, there's nothing to retry, or reboot.
+            if matches!(e, UpdateError::Disconnected(_)) {
+                return Err(e);
+            }
+
+            // A failed verify is often a one off, and nothing has been written yet, so the whole
+            // upload gets one more go from a clean NVR before we give up on it.
+            match self.stage {
+                Some(UpdateStage::Verify) => {}
+
+                // Never made it into update mode, so there's nothing to reboot out of.
+                None => return Err(e),
+                Some(_) => {
+                    self.reboot_after_failure(&arc, options);
+                    return Err(e);
+                }
+            }
+
+            warn!("Verification failed ({}), retrying upload..", e);
+            let stage = UpdateFirmwareStage(String::from("Verification failed, retrying upload"));
+            let _ = self.sender.send(Message::PageMessage(stage));
+
+            if let Err(e) = self.upload_sequence(local, &mut arc, &firmware, firmware_length) {
+                self.reboot_after_failure(&arc, options);
+                return Err(e);
+            }
         }
 
-        if let Err(e) = self.clear_nvr(&mut handle) {
-            println!("Error: {}", e);
-            self.reboot_goxlr(&mut handle);
-            return;
+        // Unlike the other stages, a failure here may leave a half written firmware on the
+        // device, and rebooting into that is the most likely way to brick it. Leave it in update
+        // mode, so the write can be run again.
+        let mut handle = arc.lock().unwrap();
+        if let Err(error) = self.device_finalise(&mut handle) {
+            return Err(disconnected_if_gone(local, error));
         }
 
-        if let Err(e) = self.upload_firmware(firmware, &mut handle) {
-            println!("Error: {}", e);
+        if reboot {
             self.reboot_goxlr(&mut handle);
-            return;
         }
+        Ok(())
+    }
 
-        if let Err(e) = self.validate_upload(firmware_length, &mut handle) {
-            println!("Error: {}", e);
-            self.reboot_goxlr(&mut handle);
-            return;
+    // Everything from putting the device into update mode, up to it accepting the firmware. None
+    // of this touches the device's firmware, so it can be safely run again from the start.
+    //
+    // If the handle goes stale part way through (for example, a USB reset re-enumerated the
+    // device), the device will have dropped out of update mode, and we can't know what state the
+    // NVR was left in. So rather than pick up from the stage which failed, we grab a fresh handle
+    // and go through the whole sequence once more.
+    fn upload_sequence(
+        &mut self,
+        local: &DeviceLocal,
+        arc: &mut Arc<Mutex<Box<dyn FullGoXLRDevice>>>,
+        firmware: &[u8],
+        firmware_length: u32,
+    ) -> Result<(), UpdateError> {
+        let result = self.upload_stages(&mut arc.lock().unwrap(), firmware, firmware_length);
+        match result {
+            Err(error) if is_stale_handle(&error.to_string()) => {
+                warn!("Handle may be stale ({}), reconnecting..", error);
+                *arc = self.reacquire_handle(local)?;
+
+                // Everything starts again from 0%, so let the user know why it's gone backwards.
+                self.send_retry(Some(String::from(tr("retry.reconnected"))));
+                self.upload_stages(&mut arc.lock().unwrap(), firmware, firmware_length)
+            }
+            result => result,
         }
+    }
 
-        if let Err(e) = self.hardware_verify(&mut handle) {
-            println!("Error: {}", e);
-            self.reboot_goxlr(&mut handle);
-            return;
+    fn upload_stages(
+        &mut self,
+        handle: &mut MutexGuard<Box<dyn FullGoXLRDevice>>,
+        firmware: &[u8],
+        firmware_length: u32,
+    ) -> Result<(), UpdateError> {
+        if let Err(error) = handle.begin_firmware_upload() {
+            return Err(UpdateError::UpdateMode(error.to_string()));
         }
+        self.clear_nvr(handle)?;
+        self.upload_firmware(firmware, handle)?;
+        self.validate_upload(firmware_length, handle)?;
+        self.hardware_verify(handle)
+    }
 
-        if let Err(e) = self.device_finalise(&mut handle) {
-            println!("Error: {}", e);
-            self.reboot_goxlr(&mut handle);
+    fn reacquire_handle(
+        &mut self,
+        local: &DeviceLocal,
+    ) -> Result<Arc<Mutex<Box<dyn FullGoXLRDevice>>>, UpdateError> {
+        let device = find_devices().into_iter().find(|device| {
+            device.bus_number() == local.bus_number
+                && device.address() == local.address
+                && device.identifier() == &local.identifier
+        });
+
+        let device = match device {
+            Some(device) => device,
+            None => {
+                let error = String::from("GoXLR is no longer connected");
+                return Err(UpdateError::Disconnected(error));
+            }
+        };
+
+        let (disconnect_sender, _) = mpsc::channel(32);
+        let (event_sender, _) = mpsc::channel(32);
+        let mut handle = match from_device(device, disconnect_sender, event_sender, true) {
+            Ok(handle) => handle,
+            Err(error) => {
+                let error = format!("Unable to reconnect to GoXLR: {}", error);
+                return Err(UpdateError::Disconnected(error));
+            }
+        };
+        handle.stop_polling();
+
+        let arc = Arc::new(Mutex::new(handle));
+        self.handles.insert(local.clone(), arc.clone());
+        Ok(arc)
+    }
+
+    pub fn reboot_device(&mut self, device: Device) {
+        let handle = self.handles.get_mut(&device.goxlr_device);
+        if handle.is_none() {
+            self.set_busy(false);
+            self.send_finish_error("Unable to retrieve GoXLR from Device");
             return;
         }
 
+        let arc = handle.unwrap().clone();
+        let mut handle = arc.lock().unwrap();
+
         self.send_finish_complete();
         self.reboot_goxlr(&mut handle);
+        self.set_busy(false);
     }
 
     fn clear_nvr(
         &mut self,
         device: &mut MutexGuard<Box<dyn FullGoXLRDevice>>,
-    ) -> Result<(), String> {
-        self.send_stage_update("Preparing Update Partition");
+    ) -> Result<(), UpdateError> {
+        self.send_stage_update(UpdateStage::ClearNvr);
 
         if let Err(error) = device.begin_erase_nvr() {
             let message = format!("Unable to start NVR Clear: {}", error);
-            self.send_finish_error(message.as_str());
-            return Err(message);
+            return Err(UpdateError::NvrClearFailed(message));
         }
 
         // Now we simply sit, wait, and update until we're done.
+        let interval = poll_interval(NVR_POLL_INTERVAL_VAR, NVR_POLL_INTERVAL);
         let mut last_percent = 0_u8;
         let mut progress = 0;
+        let mut last_change = Instant::now();
         while progress != 255 {
-            sleep(Duration::from_millis(100));
+            sleep(interval);
+            let previous = progress;
             progress = match device.poll_erase_nvr() {
                 Ok(progress) => progress,
                 Err(error) => {
                     let message = format!("Error Polling NVR Clear: {}", error);
-                    self.send_finish_error(message.as_str());
-                    return Err(message);
+                    return Err(UpdateError::NvrClearFailed(message));
                 }
             };
 
-            let percent = ((progress as f32 / 255.) * 100.) as u8;
-            if percent != last_percent {
-                last_percent = percent;
-                self.send_stage_percent(percent);
+            if progress != previous {
+                last_change = Instant::now();
+            } else if last_change.elapsed() > NVR_STALL_TIMEOUT {
+                let message = format!(
+                    "NVR clear stalled, no progress from {}% in {} seconds",
+                    last_percent,
+                    NVR_STALL_TIMEOUT.as_secs()
+                );
+                return Err(UpdateError::NvrClearFailed(message));
             }
+
+            let percent = ((progress as f32 / 255.) * 100.) as u8;
+            last_percent = percent;
+            self.send_stage_percent(percent);
         }
 
         self.send_stage_percent(100);
@@ -213,27 +704,56 @@ impl GoXLR {
 
     fn upload_firmware(
         &mut self,
-        firmware: Vec<u8>,
+        firmware: &[u8],
         device: &mut MutexGuard<Box<dyn FullGoXLRDevice>>,
-    ) -> Result<(), String> {
-        self.send_stage_update("Uploading Firmware to Device");
-        let mut last_percent = 0_u8;
+    ) -> Result<(), UpdateError> {
+        self.send_stage_update(UpdateStage::Upload);
+        let mut rate = TransferRate::new(firmware.len() as u64);
 
-        let chunk_size = 1012;
+        let mut chunk_size = upload_chunk_size();
         let mut sent = 0;
 
-        for chunk in firmware.chunks(chunk_size) {
-            if let Err(error) = device.send_firmware_packet(sent, chunk) {
-                let message = format!("Error uploading Firmware Chunk: {}", error);
-                self.send_finish_error(message.as_str());
-                return Err(message);
+        'chunks: while sent < firmware.len() as u64 {
+            let start = sent as usize;
+            let chunk = &firmware[start..firmware.len().min(start + chunk_size)];
+
+            let mut attempt = 1;
+            while let Err(error) = device.send_firmware_packet(sent, chunk) {
+                // Nothing's lost by sending the same offset again in smaller pieces.
+                if chunk_size != UPLOAD_CHUNK_SIZE {
+                    warn!(
+                        "Chunk size {} failed at offset {} ({}), falling back to {}",
+                        chunk_size, sent, error, UPLOAD_CHUNK_SIZE
+                    );
+                    chunk_size = UPLOAD_CHUNK_SIZE;
+                    continue 'chunks;
+                }
+
+                if attempt >= UPLOAD_ATTEMPTS {
+                    return Err(UpdateError::UploadFailed {
+                        offset: sent,
+                        source: error.to_string(),
+                    });
+                }
+
+                warn!("Chunk at offset {} failed ({}), retrying..", sent, error);
+                sleep(UPLOAD_RETRY_DELAY * attempt);
+                attempt += 1;
+
+                let retry = tr_fill("retry.chunk", &[&sent, &attempt]);
+                self.send_retry(Some(retry));
+            }
+
+            // Made it through after a retry, so nothing to worry about anymore.
+            if attempt > 1 {
+                self.send_retry(None);
             }
 
             sent += chunk.len() as u64;
             let percent = ((sent as f32 / firmware.len() as f32) * 100.) as u8;
-            if percent != last_percent {
-                last_percent = percent;
-                self.send_stage_percent(percent);
+            if self.send_stage_percent(percent) {
+                let rate = UpdateFirmwareRate(rate.update(sent));
+                let _ = self.sender.send(Message::PageMessage(rate));
             }
         }
 
@@ -244,40 +764,63 @@ impl GoXLR {
         &mut self,
         firmware_len: u32,
         device: &mut MutexGuard<Box<dyn FullGoXLRDevice>>,
-    ) -> Result<(), String> {
-        self.send_stage_update("Verifying File Upload");
-        let mut last_percent = 0_u8;
+    ) -> Result<(), UpdateError> {
+        self.send_stage_update(UpdateStage::Validate);
 
         let mut processed = 0_u32;
         let mut remaining_bytes = firmware_len;
         let mut hash_in = 0_u32;
 
+        // The last packet the device was happy with, so if it goes wrong we know where.
+        let mut last_good = None;
+
         while remaining_bytes > 0 {
             let (hash, count) =
                 match device.validate_firmware_packet(processed, hash_in, remaining_bytes) {
                     Ok((hash, count)) => (hash, count),
                     Err(error) => {
-                        let message = format!("Error Validating Firmware Packet: {}", error);
-                        self.send_finish_error(message.as_str());
-                        return Err(message);
+                        return Err(UpdateError::ValidateFailed {
+                            offset: processed,
+                            source: format!("{}{}", error, describe_last_packet(last_good)),
+                        });
                     }
                 };
 
-            processed += count;
-            if processed > firmware_len {
-                let message = "Error Validating Firmware, Length Mismatch";
-                self.send_finish_error(message);
-                return Err(message.to_string());
-            }
+            debug!(
+                "Validated Packet: offset {}, count {}, hash in {:#010x}, hash out {:#010x}",
+                processed, count, hash_in, hash
+            );
+
+            let next = match validated_length(processed, count, firmware_len) {
+                Ok(next) => next,
+                Err(error) => {
+                    return Err(UpdateError::ValidateFailed {
+                        offset: processed,
+                        source: format!("{}{}", error, describe_last_packet(last_good)),
+                    });
+                }
+            };
 
-            remaining_bytes -= count;
+            last_good = Some((processed, count, hash));
+            processed = next;
+            remaining_bytes = firmware_len - processed;
             hash_in = hash;
 
             let percent = ((processed as f32 / firmware_len as f32) * 100.) as u8;
-            if percent != last_percent {
-                last_percent = percent;
-                self.send_stage_percent(percent);
-            }
+            self.send_stage_percent(percent);
+        }
+
+        // The device has to have covered the whole firmware, no more and no less.
+        if processed != firmware_len {
+            return Err(UpdateError::ValidateFailed {
+                offset: processed,
+                source: format!(
+                    "Length Mismatch, validated {} of {} bytes{}",
+                    processed,
+                    firmware_len,
+                    describe_last_packet(last_good)
+                ),
+            });
         }
 
         Ok(())
@@ -286,34 +829,30 @@ impl GoXLR {
     fn hardware_verify(
         &mut self,
         device: &mut MutexGuard<Box<dyn FullGoXLRDevice>>,
-    ) -> Result<(), String> {
-        self.send_stage_update("Device Firmware Verification");
-        let mut last_percent = 0_u8;
+    ) -> Result<(), UpdateError> {
+        self.send_stage_update(UpdateStage::Verify);
 
         if let Err(error) = device.verify_firmware_status() {
             let message = format!("Unable to Start Verification: {}", error);
-            self.send_finish_error(message.as_str());
-            return Err(message);
+            return Err(UpdateError::VerifyFailed(message));
         }
 
+        let interval = poll_interval(STATUS_POLL_INTERVAL_VAR, STATUS_POLL_INTERVAL);
         let mut complete = false;
         while !complete {
+            sleep(interval);
             let (completed, total, done) = match device.poll_verify_firmware_status() {
                 Ok((completed, total, done)) => (completed, total, done),
                 Err(error) => {
                     let message = format!("Device Validation Failed: {}", error);
-                    self.send_finish_error(message.as_str());
-                    return Err(message);
+                    return Err(UpdateError::VerifyFailed(message));
                 }
             };
 
             complete = completed;
 
             let percent = ((done as f32 / total as f32) * 100.) as u8;
-            if percent != last_percent {
-                last_percent = percent;
-                self.send_stage_percent(percent);
-            }
+            self.send_stage_percent(percent);
         }
         Ok(())
     }
@@ -322,34 +861,30 @@ impl GoXLR {
     fn device_finalise(
         &mut self,
         device: &mut MutexGuard<Box<dyn FullGoXLRDevice>>,
-    ) -> Result<(), String> {
-        self.send_stage_update("Writing Firmware..");
-        let mut last_percent = 0_u8;
+    ) -> Result<(), UpdateError> {
+        self.send_stage_update(UpdateStage::Finalise);
 
         if let Err(error) = device.finalise_firmware_upload() {
             let message = format!("Unable to Start Write: {}", error);
-            self.send_finish_error(message.as_str());
-            return Err(message);
+            return Err(UpdateError::FinaliseFailed(message));
         }
 
+        let interval = poll_interval(STATUS_POLL_INTERVAL_VAR, STATUS_POLL_INTERVAL);
         let mut complete = false;
         while !complete {
+            sleep(interval);
             let (completed, total, done) = match device.poll_finalise_firmware_upload() {
                 Ok((completed, total, done)) => (completed, total, done),
                 Err(error) => {
                     let message = format!("Progress Check Failed: {}", error);
-                    self.send_finish_error(message.as_str());
-                    return Err(message);
+                    return Err(UpdateError::FinaliseFailed(message));
                 }
             };
 
             complete = completed;
 
             let percent = ((done as f32 / total as f32) * 100.) as u8;
-            if percent != last_percent {
-                last_percent = percent;
-                self.send_stage_percent(percent);
-            }
+            self.send_stage_percent(percent);
         }
         Ok(())
     }
@@ -358,39 +893,168 @@ impl GoXLR {
         let _ = device.reboot_after_firmware_upload();
     }
 
-    fn send_stage_percent(&self, percent: u8) {
+    fn reboot_after_failure(
+        &mut self,
+        arc: &Arc<Mutex<Box<dyn FullGoXLRDevice>>>,
+        options: UpdateOptions,
+    ) {
+        if options.stay_in_update_mode_on_failure {
+            warn!("Update failed, leaving the GoXLR in update mode");
+            return;
+        }
+        self.reboot_goxlr(&mut arc.lock().unwrap());
+    }
+
+    // This is called on every poll, whether or not the percentage has changed, and compares
+    // against what was last sent, so nothing is lost by being held back. Returns whether it was
+    // sent, so anything else which goes along with it can be held back too.
+    fn send_stage_percent(&mut self, percent: u8) -> bool {
+        if let Some((sent, last)) = self.percent_sent {
+            let start_or_end = percent == 0 || percent >= 100;
+            if percent == last || (!start_or_end && sent.elapsed() < PERCENT_INTERVAL) {
+                return false;
+            }
+        }
+        self.percent_sent = Some((Instant::now(), percent));
+
+        if let Some(stage) = self.stage {
+            let overall = UpdateFirmwareOverallPercent(stage.overall_percent(percent));
+            let _ = self.sender.send(Message::PageMessage(overall));
+        }
+
         let percent = UpdateFirmwarePercent(percent);
         let _ = self.sender.send(Message::PageMessage(percent));
+        true
     }
 
-    fn send_stage_update(&self, stage: &str) {
-        let stage = UpdateFirmwareStage(stage.to_string());
+    fn send_stage_update(&mut self, stage: UpdateStage) {
+        self.stage = Some(stage);
+        self.percent_sent = None;
+
+        let stage = UpdateFirmwareStage(stage.label().to_string());
         let _ = self.sender.send(Message::PageMessage(stage));
 
         self.send_stage_percent(0);
     }
 
-    fn send_setup_error(&self, message: &str) {
-        let stage = UpdateFirmwareStage("Preparing...".to_string());
+    fn send_retry(&self, retry: Option<String>) {
+        let _ = self
+            .sender
+            .send(Message::PageMessage(UpdateFirmwareRetry(retry)));
+    }
+
+    pub fn send_setup_error(&self, message: &str) {
+        let stage = UpdateFirmwareStage(tr("stage.preparing").to_string());
         let percent = UpdateFirmwarePercent(0);
 
         let _ = self.sender.send(Message::PageMessage(stage));
         let _ = self.sender.send(Message::PageMessage(percent));
-        self.send_finish_error(message);
+
+        // Nothing was sent to the device, so it's worth telling apart from a failed update.
+        self.send_failure(message, Outcome::SetupFailed);
     }
 
     fn send_finish_complete(&self) {
-        let message = "Your GoXLR Has updated Successfully!";
-        let message = UpdateFirmwareMessage(message.to_string());
+        let message = UpdateFirmwareMessage(tr("update.complete").to_string());
 
         let percent = UpdateFirmwarePercent(100);
+        let overall = UpdateFirmwareOverallPercent(100);
 
         let _ = self.sender.send(Message::PageMessage(message));
         let _ = self.sender.send(Message::PageMessage(percent));
+        let _ = self.sender.send(Message::PageMessage(overall));
+        let _ = self
+            .sender
+            .send(Message::StepsMessage(StepMessages::UpdateFinished(
+                Outcome::Success,
+            )));
         self.send_finish();
     }
 
+    fn send_dry_run_complete(&self, device: &Device, details: &FirmwareDetails) {
+        let change = details.version.cmp_to(&device.version);
+        let description = change.describe(device.version, details.version);
+        let message = tr_fill("update.dry-run-complete", &[&description]);
+        let message = UpdateFirmwareMessage(message);
+
+        let percent = UpdateFirmwarePercent(100);
+        let overall = UpdateFirmwareOverallPercent(100);
+
+        let _ = self.sender.send(Message::PageMessage(message));
+        let _ = self.sender.send(Message::PageMessage(percent));
+        let _ = self.sender.send(Message::PageMessage(overall));
+        self.send_finish();
+    }
+
+    fn send_awaiting_reboot(&self) {
+        let message = tr_fill("update.awaiting-reboot", &[&tr("update.reboot")]);
+        let message = UpdateFirmwareMessage(message);
+
+        let percent = UpdateFirmwarePercent(100);
+        let awaiting = UpdateFirmwareAwaitingReboot(true);
+
+        let _ = self.sender.send(Message::PageMessage(message));
+        let _ = self.sender.send(Message::PageMessage(percent));
+        let _ = self.sender.send(Message::PageMessage(awaiting));
+    }
+
+    fn send_batch_status(&self, index: usize, status: BatchStatus) {
+        let status = PageMessages::UpdateBatchStatus(index, status);
+        let _ = self.sender.send(Message::PageMessage(status));
+    }
+
+    fn send_batch_finish(&self, succeeded: usize, failed: usize, skipped: usize, dry_run: bool) {
+        let mut message = match (dry_run, failed == 0 && skipped == 0) {
+            (false, true) => tr_fill("batch.complete", &[&succeeded]),
+            (false, false) => tr_fill("batch.failed", &[&succeeded, &failed]),
+            (true, true) => tr_fill("batch.dry-run-complete", &[&succeeded]),
+            (true, false) => tr_fill("batch.dry-run-failed", &[&succeeded, &failed]),
+        };
+        if skipped > 0 {
+            message = format!("{} {}", message, tr_fill("batch.skipped", &[&skipped]));
+        }
+        let message = UpdateFirmwareMessage(message);
+
+        let percent = UpdateFirmwarePercent(100);
+        let overall = UpdateFirmwareOverallPercent(100);
+        let is_error = UpdateFirmwareIsError(failed > 0);
+
+        let _ = self.sender.send(Message::PageMessage(message));
+        let _ = self.sender.send(Message::PageMessage(percent));
+        let _ = self.sender.send(Message::PageMessage(overall));
+        let _ = self.sender.send(Message::PageMessage(is_error));
+
+        // Nothing was flashed on a dry run, so there's no result to hand on.
+        if !dry_run {
+            let outcome = if failed == 0 {
+                Outcome::Success
+            } else {
+                Outcome::UpdateFailed
+            };
+            let _ = self
+                .sender
+                .send(Message::StepsMessage(StepMessages::UpdateFinished(outcome)));
+        }
+        self.send_finish();
+    }
+
+    fn send_write_incomplete(&self, message: &str) {
+        let incomplete = UpdateFirmwareWriteIncomplete(true);
+        let _ = self.sender.send(Message::PageMessage(incomplete));
+        self.send_finish_error(message);
+    }
+
+    fn send_disconnected(&self, message: &str) {
+        let disconnected = UpdateFirmwareDisconnected(true);
+        let _ = self.sender.send(Message::PageMessage(disconnected));
+        self.send_finish_error(message);
+    }
+
     fn send_finish_error(&self, message: &str) {
+        self.send_failure(message, Outcome::UpdateFailed);
+    }
+
+    fn send_failure(&self, message: &str, outcome: Outcome) {
         let is_error = UpdateFirmwareIsError(true);
 
         let message = format!("Error: {}", message);
@@ -398,6 +1062,9 @@ impl GoXLR {
 
         let _ = self.sender.send(Message::PageMessage(message));
         let _ = self.sender.send(Message::PageMessage(is_error));
+        let _ = self
+            .sender
+            .send(Message::StepsMessage(StepMessages::UpdateFinished(outcome)));
 
         self.send_finish();
     }
@@ -408,6 +1075,410 @@ impl GoXLR {
     }
 }
 
+fn open_handle(device: GoXLRDevice, skip_pause: bool) -> Result<Box<dyn FullGoXLRDevice>, String> {
+    // We don't care about messages being sent out at this point, we're explicitly going to ignore
+    // them and handle errors on-the-fly during the update.
+    let (disconnect_sender, _) = mpsc::channel(32);
+    let (event_sender, _) = mpsc::channel(32);
+
+    // The final flag skips the pause that waits for the startup animation to finish.
+    let mut handle = match from_device(device, disconnect_sender, event_sender, skip_pause) {
+        Ok(handle) => handle,
+        Err(error) => return Err(format!("Unable to open device: {}", error)),
+    };
+
+    // Nothing is listening for events, so stop it polling for them.
+    handle.stop_polling();
+    Ok(handle)
+}
+
+fn confirm_serial(
+    handle: &mut MutexGuard<Box<dyn FullGoXLRDevice>>,
+    expected: &str,
+) -> Result<(), String> {
+    match handle.get_serial_number() {
+        Ok((serial, _)) if serial == expected => Ok(()),
+        Ok((serial, _)) => Err(format!(
+            "The connected GoXLR ({}) is not the one selected ({}), please go back and select it \
+            again",
+            serial, expected
+        )),
+        Err(error) => Err(format!(
+            "Unable to confirm the GoXLR's Serial Number: {}",
+            error
+        )),
+    }
+}
+
+fn read_device_details(
+    handle: &mut MutexGuard<Box<dyn FullGoXLRDevice>>,
+) -> Result<(DeviceType, String, VersionNumber, HardwareRevision), String> {
+    let descriptor = match handle.get_descriptor() {
+        Ok(descriptor) => descriptor,
+        Err(error) => return Err(format!("Unable to read USB Descriptor: {}", error)),
+    };
+
+    let device_type = match descriptor.product_id() {
+        goxlr_usb::PID_GOXLR_FULL => DeviceType::Full,
+        goxlr_usb::PID_GOXLR_MINI => DeviceType::Mini,
+        product_id => return Err(format!("Unrecognised Product ID: {:#06x}", product_id)),
+    };
+    let release = descriptor.device_version();
+    let revision = HardwareRevision(release.major(), release.minor(), release.sub_minor());
+
+    let device_serial = match handle.get_serial_number() {
+        Ok((serial, _)) => serial,
+        Err(error) => return Err(format!("Unable to read Serial Number: {}", error)),
+    };
+    if device_serial.is_empty() {
+        return Err(String::from("Device reported an empty Serial Number"));
+    }
+
+    let version = match handle.get_firmware_version() {
+        Ok(firmware) => VersionNumber(
+            firmware.firmware.0,
+            firmware.firmware.1,
+            firmware.firmware.2,
+            firmware.firmware.3,
+        ),
+        Err(error) => return Err(format!("Unable to read Firmware Version: {}", error)),
+    };
+
+    Ok((device_type, device_serial, version, revision))
+}
+
+// Re-reads the firmware from disk in the same way it was checked when it was selected, and makes
+// sure it's actually meant for this device.
+fn check_compatibility(device: &Device, details: &FirmwareDetails) -> Result<(), String> {
+    let info = check_firmware(details.path.clone())?;
+    if info.device != device.device_type {
+        return Err(format!(
+            "Firmware is not for the GoXLR {:?}",
+            device.device_type
+        ));
+    }
+    Ok(())
+}
+
+fn record_current_firmware(device: &Device) -> Result<PathBuf, String> {
+    let dirs = match ProjectDirs::from("", "", "goxlr-firmware-updater") {
+        Some(dirs) => dirs,
+        None => return Err(String::from("Unable to locate the data directory")),
+    };
+    let record_dir = dirs.data_dir().join("version-records");
+    if fs::create_dir_all(&record_dir).is_err() {
+        return Err(String::from(
+            "Unable to create the version record directory",
+        ));
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    // This only says what was installed, so make sure nobody mistakes it for something which can
+    // be restored from.
+    let mut record = format!(
+        "# The firmware this GoXLR was running before it was updated, this is not a copy of it\n\
+        Serial: {}\nDevice: {:?}\nFirmware: {}\nRecorded: {}\n",
+        device.device_serial, device.device_type, device.version, timestamp
+    );
+
+    // If we've got a copy of that firmware lying around, point at it.
+    let local = stored_firmware()
+        .into_iter()
+        .find(|(_, info, _)| info.device == device.device_type && info.version == device.version);
+    if let Some((path, _, _)) = local {
+        record.push_str(&format!("File: {}\n", path.to_string_lossy()));
+    }
+
+    let path = record_dir.join(format!("{}-{}.txt", device.device_serial, timestamp));
+    if fs::write(&path, record).is_err() {
+        return Err(String::from("Unable to write the version record"));
+    }
+    Ok(path)
+}
+
+fn target_serial() -> Option<String> {
+    match std::env::var(TARGET_SERIAL_VAR) {
+        Ok(serial) if !serial.trim().is_empty() => {
+            info!("Only looking for the GoXLR with serial {}", serial.trim());
+            Some(serial.trim().to_string())
+        }
+        _ => None,
+    }
+}
+
+fn describe_ignored(device: &DeviceLocal, reason: &str) -> String {
+    format!(
+        "Bus {}, Address {}: {}",
+        device.bus_number, device.address, reason
+    )
+}
+
+// We don't get a typed error back from the device, so go by what libusb says when the device
+// it was talking to has gone away underneath it.
+fn is_stale_handle(error: &str) -> bool {
+    let error = error.to_lowercase();
+    error.contains("no such device") || error.contains("input/output error")
+}
+
+fn upload_chunk_size() -> usize {
+    match std::env::var(UPLOAD_CHUNK_SIZE_VAR).map(|value| value.parse::<usize>()) {
+        Ok(Ok(size)) if size > 0 => {
+            info!("Uploading firmware in chunks of {} bytes", size);
+            size
+        }
+        Ok(_) => {
+            let var = UPLOAD_CHUNK_SIZE_VAR;
+            warn!("Ignoring invalid {}, using {}", var, UPLOAD_CHUNK_SIZE);
+            UPLOAD_CHUNK_SIZE
+        }
+        Err(_) => UPLOAD_CHUNK_SIZE,
+    }
+}
+
+fn poll_interval(var: &str, default: Duration) -> Duration {
+    match std::env::var(var).map(|value| value.parse::<u64>()) {
+        Ok(Ok(millis)) if millis > 0 => {
+            info!("Polling every {}ms ({})", millis, var);
+            Duration::from_millis(millis)
+        }
+        Ok(_) => {
+            warn!("Ignoring invalid {}, using {:?}", var, default);
+            default
+        }
+        Err(_) => default,
+    }
+}
+
+// An error which looks like the handle has gone stale, from a device which is no longer there, is
+// the device being unplugged.
+fn disconnected_if_gone(local: &DeviceLocal, error: UpdateError) -> UpdateError {
+    let connected = find_devices().iter().any(|device| {
+        device.bus_number() == local.bus_number
+            && device.address() == local.address
+            && device.identifier() == &local.identifier
+    });
+
+    if !connected && is_stale_handle(&error.to_string()) {
+        return UpdateError::Disconnected(error.to_string());
+    }
+    error
+}
+
+// Works out where validation has got to after the device reports `count` bytes from `processed`.
+// A packet which covers nothing would never finish, and one which runs off the end of the
+// firmware means the device and us disagree about what was uploaded.
+fn validated_length(processed: u32, count: u32, firmware_len: u32) -> Result<u32, String> {
+    if count == 0 {
+        return Err(format!(
+            "Device validated no bytes at offset {} of {}",
+            processed, firmware_len
+        ));
+    }
+
+    match processed.checked_add(count) {
+        Some(next) if next <= firmware_len => Ok(next),
+        _ => Err(format!(
+            "Length Mismatch, count {} at offset {} overruns the {} byte firmware by {} bytes",
+            count,
+            processed,
+            firmware_len,
+            u64::from(processed) + u64::from(count) - u64::from(firmware_len)
+        )),
+    }
+}
+
+// Formats the (offset, count, hash) of the last validated packet for error messages.
+fn describe_last_packet(last_good: Option<(u32, u32, u32)>) -> String {
+    match last_good {
+        Some((offset, count, hash)) => format!(
+            " (last good packet: offset {}, count {}, hash {:#010x})",
+            offset, count, hash
+        ),
+        None => String::from(" (no packets validated)"),
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum UpdateStage {
+    ClearNvr,
+    Upload,
+    Validate,
+    Verify,
+    Finalise,
+}
+
+impl UpdateStage {
+    const ALL: [UpdateStage; 5] = [
+        UpdateStage::ClearNvr,
+        UpdateStage::Upload,
+        UpdateStage::Validate,
+        UpdateStage::Verify,
+        UpdateStage::Finalise,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            UpdateStage::ClearNvr => tr("stage.clear-nvr"),
+            UpdateStage::Upload => tr("stage.upload"),
+            UpdateStage::Validate => tr("stage.validate"),
+            UpdateStage::Verify => tr("stage.verify"),
+            UpdateStage::Finalise => tr("stage.finalise"),
+        }
+    }
+
+    // What to tell the user if this stage fails, and what they can do about it.
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            UpdateStage::ClearNvr => tr("stage.clear-nvr.failed"),
+            UpdateStage::Upload => tr("stage.upload.failed"),
+            UpdateStage::Validate => tr("stage.validate.failed"),
+            UpdateStage::Verify => tr("stage.verify.failed"),
+            UpdateStage::Finalise => tr("stage.finalise.failed"),
+        }
+    }
+
+    // Roughly how much of the overall update each stage takes, these need to add up to 100. The
+    // NVR clear is by far the slowest part of the process.
+    fn weight(&self) -> u8 {
+        match self {
+            UpdateStage::ClearNvr => 40,
+            UpdateStage::Upload => 30,
+            UpdateStage::Validate => 10,
+            UpdateStage::Verify => 10,
+            UpdateStage::Finalise => 10,
+        }
+    }
+
+    // Maps a percentage within this stage, to a percentage of the update as a whole.
+    fn overall_percent(&self, percent: u8) -> u8 {
+        let completed: u32 = UpdateStage::ALL
+            .iter()
+            .take_while(|stage| *stage != self)
+            .map(|stage| stage.weight() as u32)
+            .sum();
+
+        let current = (self.weight() as u32 * percent.min(100) as u32) / 100;
+        (completed + current) as u8
+    }
+}
+
+// Why an update stopped. Each has a short code which can be quoted in a bug report, alongside the
+// technical detail from the device.
+#[derive(Debug, Clone)]
+pub enum UpdateError {
+    Setup(String),
+    UpdateMode(String),
+    Disconnected(String),
+    NvrClearFailed(String),
+    UploadFailed { offset: u64, source: String },
+    ValidateFailed { offset: u32, source: String },
+    VerifyFailed(String),
+    FinaliseFailed(String),
+}
+
+impl UpdateError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            UpdateError::Setup(_) => "E-SETUP-01",
+            UpdateError::UpdateMode(_) => "E-MODE-01",
+            UpdateError::Disconnected(_) => "E-USB-01",
+            UpdateError::NvrClearFailed(_) => "E-NVR-01",
+            UpdateError::UploadFailed { .. } => "E-UPLOAD-01",
+            UpdateError::ValidateFailed { .. } => "E-VALIDATE-01",
+            UpdateError::VerifyFailed(_) => "E-VERIFY-01",
+            UpdateError::FinaliseFailed(_) => "E-WRITE-01",
+        }
+    }
+}
+
+impl Display for UpdateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let code = self.code();
+        match self {
+            UpdateError::Setup(error) => write!(f, "{} Preparing: {}", code, error),
+            UpdateError::UpdateMode(error) => {
+                write!(f, "{} Entering Update Mode: {}", code, error)
+            }
+            UpdateError::Disconnected(error) => write!(f, "{} Reconnecting: {}", code, error),
+            UpdateError::NvrClearFailed(error) => {
+                write!(f, "{} {}: {}", code, UpdateStage::ClearNvr.label(), error)
+            }
+            UpdateError::UploadFailed { offset, source } => write!(
+                f,
+                "{} {}: Chunk at {}: {}",
+                code,
+                UpdateStage::Upload.label(),
+                offset,
+                source
+            ),
+            UpdateError::ValidateFailed { offset, source } => write!(
+                f,
+                "{} {}: Packet at {}: {}",
+                code,
+                UpdateStage::Validate.label(),
+                offset,
+                source
+            ),
+            UpdateError::VerifyFailed(error) => {
+                write!(f, "{} {}: {}", code, UpdateStage::Verify.label(), error)
+            }
+            UpdateError::FinaliseFailed(error) => write!(
+                f,
+                "{} {}: {} (GoXLR left in update mode)",
+                code,
+                UpdateStage::Finalise.label(),
+                error
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct UpdateOptions {
+    // Don't automatically reboot the GoXLR once the firmware has been written, wait for the
+    // user to trigger it.
+    pub pause_before_reboot: bool,
+
+    // The user has acknowledged that the firmware is on the blocklist, and wants it anyway.
+    pub allow_blocked_firmware: bool,
+
+    // Run all the checks, but stop before anything is sent to the device.
+    pub dry_run: bool,
+
+    // For recovery, if the update fails leave the GoXLR in update mode rather than rebooting it
+    // into whatever's on there, so the upload can be run straight away again.
+    pub stay_in_update_mode_on_failure: bool,
+
+    // Once the update's done, wait for the GoXLR to come back, and check what it's now running.
+    pub check_after_reboot: bool,
+}
+
+// Where a single device is at in a batch update.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BatchStatus {
+    Pending,
+    Updating,
+    Complete,
+    Failed(String),
+    Skipped(String),
+}
+
+impl BatchStatus {
+    pub fn label(&self) -> String {
+        match self {
+            BatchStatus::Pending => tr("batch.status.pending").to_string(),
+            BatchStatus::Updating => tr("batch.status.updating").to_string(),
+            BatchStatus::Complete => tr("batch.status.complete").to_string(),
+            BatchStatus::Failed(error) => tr_fill("batch.status.failed", &[error]),
+            BatchStatus::Skipped(reason) => tr_fill("batch.status.skipped", &[reason]),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct DeviceLocal {
     pub(crate) bus_number: u8,
@@ -420,5 +1491,75 @@ pub struct Device {
     pub device_type: DeviceType,
     pub device_serial: String,
     pub version: VersionNumber,
+    pub hardware_revision: HardwareRevision,
     pub goxlr_device: DeviceLocal,
+
+    // Seen disconnecting while we were looking for it.
+    pub connection_unstable: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_empty_validation() {
+        assert!(validated_length(0, 0, 1000).is_err());
+        assert!(validated_length(500, 0, 1000).is_err());
+    }
+
+    #[test]
+    fn rejects_validation_past_the_end() {
+        assert!(validated_length(900, 101, 1000).is_err());
+
+        // Big enough to wrap a u32, which mustn't come out looking like a small offset.
+        assert!(validated_length(900, u32::MAX, 1000).is_err());
+    }
+
+    #[test]
+    fn accepts_validation_up_to_the_end() {
+        assert_eq!(validated_length(0, 400, 1000), Ok(400));
+        assert_eq!(validated_length(400, 600, 1000), Ok(1000));
+    }
+
+    // A key missing from the translation comes back as the key itself.
+    #[test]
+    fn every_stage_is_translated() {
+        for stage in UpdateStage::ALL {
+            assert!(!stage.label().starts_with("stage."));
+            assert!(!stage.explanation().starts_with("stage."));
+        }
+    }
+
+    fn sent_percents(receiver: &mut mpsc::UnboundedReceiver<Message>) -> Vec<u8> {
+        let mut sent = vec![];
+        while let Ok(message) = receiver.try_recv() {
+            if let Message::PageMessage(UpdateFirmwarePercent(percent)) = message {
+                sent.push(percent);
+            }
+        }
+        sent
+    }
+
+    #[test]
+    fn held_back_percent_is_sent_later() {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let mut goxlr = GoXLR::new(sender, false);
+        goxlr.send_stage_update(UpdateStage::Upload);
+        assert_eq!(sent_percents(&mut receiver), vec![0]);
+
+        // Too soon after the 0, so it has to wait..
+        assert!(!goxlr.send_stage_percent(47));
+        assert!(sent_percents(&mut receiver).is_empty());
+
+        // ..but goes out on the next poll after the interval, even though it hasn't changed.
+        sleep(PERCENT_INTERVAL);
+        assert!(goxlr.send_stage_percent(47));
+        assert!(!goxlr.send_stage_percent(47));
+        assert_eq!(sent_percents(&mut receiver), vec![47]);
+
+        // The end of a stage is never held back.
+        assert!(goxlr.send_stage_percent(100));
+        assert_eq!(sent_percents(&mut receiver), vec![100]);
+    }
 }
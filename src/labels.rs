@@ -0,0 +1,58 @@
+use crate::config::{parse_device, read_config_lines};
+use crate::firmware::VersionNumber;
+use crate::DeviceType;
+use log::warn;
+use std::sync::OnceLock;
+
+// Friendlier names for firmware releases (Device, Version, Label), these are only ever used for
+// display, everything else goes by the version number.
+const RELEASE_LABELS: &[(DeviceType, VersionNumber, &str)] = &[];
+
+// Extra labels can be added locally, one per line, in the form:
+//   <full|mini|any> <version> <label>
+const LABELS_FILE: &str = "version-labels.txt";
+
+static LABELS: OnceLock<Vec<(Option<DeviceType>, VersionNumber, String)>> = OnceLock::new();
+
+// Formats a version for display, with its friendly name if we know it.
+pub fn version_label(device_type: DeviceType, version: VersionNumber) -> String {
+    let labels = LABELS.get_or_init(load_labels);
+    let label = labels.iter().find(|(device, label_version, _)| {
+        (device.is_none() || *device == Some(device_type)) && *label_version == version
+    });
+
+    match label {
+        Some((_, _, label)) => format!("{} ({})", version, label),
+        None => version.to_string(),
+    }
+}
+
+fn load_labels() -> Vec<(Option<DeviceType>, VersionNumber, String)> {
+    let mut labels: Vec<_> = RELEASE_LABELS
+        .iter()
+        .map(|(device, version, label)| (Some(*device), *version, label.to_string()))
+        .collect();
+
+    // Local labels go first, so they can override the ones we ship.
+    let mut local = vec![];
+    for line in read_config_lines(LABELS_FILE) {
+        match parse_label(&line) {
+            Ok(label) => local.push(label),
+            Err(error) => warn!("Ignoring Version Label '{}': {}", line, error),
+        }
+    }
+    local.append(&mut labels);
+    local
+}
+
+fn parse_label(line: &str) -> Result<(Option<DeviceType>, VersionNumber, String), String> {
+    let mut parts = line.splitn(3, char::is_whitespace);
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(device), Some(version), Some(label)) => Ok((
+            parse_device(device)?,
+            version.parse::<VersionNumber>()?,
+            label.trim().to_string(),
+        )),
+        _ => Err(String::from("Expected <device> <version> <label>")),
+    }
+}
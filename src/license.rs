@@ -0,0 +1,37 @@
+use crate::config::{config_path, read_config_lines};
+use crate::firmware::sha256_hex;
+use crate::{LICENSE, LICENSE_3RD_PARTY};
+use log::{info, warn};
+use std::fs;
+
+// Holds a hash of the license texts the user agreed to, if they change, the hash won't match and
+// they'll be asked again.
+const ACCEPTANCE_FILE: &str = "license-accepted.txt";
+
+fn license_hash() -> String {
+    sha256_hex(format!("{}{}", LICENSE, LICENSE_3RD_PARTY).as_bytes())
+}
+
+pub fn licenses_accepted() -> bool {
+    read_config_lines(ACCEPTANCE_FILE).contains(&license_hash())
+}
+
+pub fn save_license_acceptance() {
+    let path = match config_path(ACCEPTANCE_FILE) {
+        Some(path) => path,
+        None => return,
+    };
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    let contents = format!(
+        "# Licenses accepted, delete this file to be asked again\n{}\n",
+        license_hash()
+    );
+    match fs::write(&path, contents) {
+        Ok(()) => info!("License acceptance saved to {}", path.to_string_lossy()),
+        Err(error) => warn!("Unable to save license acceptance: {}", error),
+    }
+}
@@ -0,0 +1,81 @@
+use log::warn;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+// The wizard's text, one file per language, embedded so there's nothing extra to ship. Each line
+// is 'key = text', with '\n' for a line break. The first language is the default, and anything
+// missing from a translation falls back to it.
+const LANGUAGES: &[(&str, &str, &str)] = &[("en", "English", include_str!("../locales/en.txt"))];
+
+static TRANSLATIONS: OnceLock<Vec<HashMap<&'static str, String>>> = OnceLock::new();
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Language(usize);
+
+impl Display for Language {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", LANGUAGES[self.0].1)
+    }
+}
+
+pub fn languages() -> Vec<Language> {
+    (0..LANGUAGES.len()).map(Language).collect()
+}
+
+pub fn current_language() -> Language {
+    Language(CURRENT.load(Ordering::Relaxed))
+}
+
+pub fn set_language(language: Language) {
+    CURRENT.store(language.0, Ordering::Relaxed);
+}
+
+// Looks up the text for a key in the current language. A key nobody has translated is returned
+// as is, so it's obvious on screen which one is missing.
+pub fn tr(key: &'static str) -> &'static str {
+    let translations = TRANSLATIONS.get_or_init(load_translations);
+    [current_language().0, 0]
+        .iter()
+        .find_map(|index| translations[*index].get(key))
+        .map(String::as_str)
+        .unwrap_or(key)
+}
+
+// As above, with each '{}' in the text replaced by the next value, in order.
+pub fn tr_fill(key: &'static str, values: &[&dyn Display]) -> String {
+    let mut parts = tr(key).split("{}");
+    let mut filled = parts.next().unwrap_or_default().to_string();
+    for (index, part) in parts.enumerate() {
+        if let Some(value) = values.get(index) {
+            filled.push_str(&value.to_string());
+        }
+        filled.push_str(part);
+    }
+    filled
+}
+
+fn load_translations() -> Vec<HashMap<&'static str, String>> {
+    LANGUAGES
+        .iter()
+        .map(|(code, _, resource)| {
+            let mut translation = HashMap::new();
+            for line in resource.lines().map(str::trim) {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                match line.split_once('=') {
+                    Some((key, text)) => {
+                        let text = text.trim().replace("\\n", "\n");
+                        translation.insert(key.trim(), text);
+                    }
+                    None => warn!("Ignoring '{}' in the {} translation", line, code),
+                }
+            }
+            translation
+        })
+        .collect()
+}
@@ -0,0 +1,83 @@
+use directories::ProjectDirs;
+use env_logger::{Env, Target};
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const LOG_NAME: &str = "goxlr-firmware-updater.log";
+
+// How many logs from previous runs are kept alongside the current one.
+const LOG_HISTORY: usize = 3;
+
+static LOG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+// When launched by double-clicking there's no console to read, so everything also goes to a file
+// which can be attached to a bug report. RUST_LOG still controls how much is logged.
+pub fn init_logging() {
+    let mut builder = env_logger::Builder::from_env(Env::default().default_filter_or("info"));
+    if let Some((path, file)) = open_log_file() {
+        builder.target(Target::Pipe(Box::new(LogWriter { file })));
+        let _ = LOG_PATH.set(path);
+    }
+    builder.init();
+}
+
+pub fn log_path() -> Option<&'static PathBuf> {
+    LOG_PATH.get()
+}
+
+// Next to the executable is easiest for people to find, but that won't be writable if it's been
+// installed somewhere system wide.
+fn open_log_file() -> Option<(PathBuf, File)> {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+    let data_dir = ProjectDirs::from("", "", "goxlr-firmware-updater")
+        .map(|dirs| dirs.data_dir().join("logs"));
+
+    for dir in [exe_dir, data_dir].into_iter().flatten() {
+        if fs::create_dir_all(&dir).is_err() {
+            continue;
+        }
+
+        let path = dir.join(LOG_NAME);
+        rotate_logs(&path);
+        if let Ok(file) = File::create(&path) {
+            return Some((path, file));
+        }
+    }
+    None
+}
+
+fn rotate_logs(path: &Path) {
+    let numbered = |index: usize| path.with_extension(format!("log.{}", index));
+
+    for index in (1..LOG_HISTORY).rev() {
+        if numbered(index).exists() {
+            let _ = fs::rename(numbered(index), numbered(index + 1));
+        }
+    }
+    if path.exists() {
+        let _ = fs::rename(path, numbered(1));
+    }
+}
+
+// Keeps the console output for anyone running from a terminal, as well as writing the file.
+struct LogWriter {
+    file: File,
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let _ = std::io::stderr().write_all(buf);
+        self.file.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let _ = std::io::stderr().flush();
+        self.file.flush()
+    }
+}
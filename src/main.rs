@@ -1,46 +1,252 @@
+mod cli;
+mod compat;
+mod config;
+mod crash;
 mod downloader;
 mod firmware;
 mod goxlr;
+mod labels;
+mod license;
+mod locale;
+mod logging;
+mod monitor;
+mod outcome;
+mod policy;
 mod preflight;
-
-use crate::downloader::download_firmware;
-use crate::firmware::VersionNumber;
-use crate::goxlr::{Device, GoXLR};
-use crate::preflight::status_check;
+mod release;
+mod scroll;
+mod taskbar;
+mod transfer;
+
+use crate::cli::{run_cli, CliOptions};
+use crate::compat::required_app_version;
+use crate::downloader::{
+    check_download_url, check_latest_firmware, clear_stored_firmware, download_dir,
+    download_firmware, open_download_dir, set_download_dir, stored_firmware,
+};
+use crate::firmware::{check_firmware_hashed, FirmwareInfo, VersionChange, VersionNumber};
+use crate::goxlr::{BatchStatus, Device, GoXLR, UpdateOptions};
+use crate::labels::version_label;
+use crate::license::{licenses_accepted, save_license_acceptance};
+use crate::locale::{current_language, languages, set_language, tr, tr_fill, Language};
+use crate::logging::{init_logging, log_path};
+use crate::monitor::monitor_progress;
+use crate::outcome::{exit_code, set_outcome, Outcome};
+use crate::policy::{check_policy, FirmwarePolicy};
+use crate::preflight::{
+    build_version, close_apps, fast_startup_enabled, installed_app_version, log_environment,
+    status_check,
+};
+use crate::release::{check_for_release, open_release_page, release_page};
+use crate::scroll::FitCheck;
+use crate::taskbar::{set_taskbar_progress, TaskbarProgress};
+use crate::transfer::TransferStats;
+use iced::event::Status;
+use iced::keyboard::KeyCode;
 use iced::widget::{
-    button, checkbox, column, container, horizontal_space, progress_bar, radio, row, scrollable,
-    text, Rule, Space,
+    button, checkbox, column, container, horizontal_space, pick_list, progress_bar, radio, row,
+    scrollable, text, text_input, tooltip, Button, Rule, Space,
 };
 use iced::{
-    executor, window, Application, Command, Element, Length, Padding, Renderer, Settings,
-    Subscription, Theme,
+    executor, keyboard, window, Alignment, Application, Command, Element, Event, Length, Padding,
+    Renderer, Settings, Subscription, Theme,
 };
+use log::{debug, error, info, warn};
 use rfd::FileDialog;
-use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::path::PathBuf;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 
+const WINDOW_TITLE: &str = "GoXLR Firmware Updater";
+
 const LICENSE: &str = include_str!("../LICENSE");
 const LICENSE_3RD_PARTY: &str = include_str!("../LICENSE-3RD-PARTY");
 
-fn main() -> iced::Result {
-    Pages::run(Settings {
+const LICENSE_URL: &str =
+    "https://github.com/FrostyCoolSlug/goxlr-firmware-updater/blob/main/LICENSE";
+const LICENSE_3RD_PARTY_URL: &str =
+    "https://github.com/FrostyCoolSlug/goxlr-firmware-updater/blob/main/LICENSE-3RD-PARTY";
+
+const BUSY_MESSAGE: &str = "An update is in progress, please wait for it to finish";
+
+// The exit code says how the run ended, for anyone scripting the updater (see outcome.rs):
+//   0  Success, the firmware was flashed
+//   1  Incomplete, the updater was closed (or stopped) before the update finished
+//   2  No GoXLR was found, or none matched --device
+//   3  The firmware was invalid, not for this device, or not newer without --allow-downgrade
+//   4  The firmware download failed
+//   5  The update failed part way through, uploading, verifying or writing the firmware
+//   6  The update couldn't be set up, nothing was sent to the GoXLR
+fn main() -> ExitCode {
+    // Checked before anything else, so asking for the version doesn't rotate the logs.
+    if has_argument("--version") || has_argument("-V") {
+        println!("goxlr-firmware-updater {}", build_version());
+        println!("goxlr-usb {}", env!("GOXLR_USB_VERSION"));
+        return ExitCode::SUCCESS;
+    }
+
+    init_logging();
+    crash::install_panic_hook();
+    log_environment();
+
+    // Useful for debugging, print everything we know about a firmware file and bail.
+    if let Some(path) = argument_path("--inspect-firmware") {
+        inspect_firmware(&path);
+        return ExitCode::SUCCESS;
+    }
+
+    // For machines with no display (or anyone who'd rather not click through the wizard).
+    if has_argument("--cli") {
+        return run_cli(CliOptions {
+            serial: argument_value("--device"),
+            file: argument_path("--file"),
+            download_url: argument_value("--download-url"),
+            allow_downgrade: has_argument("--allow-downgrade"),
+            wait_for_startup: has_argument("--wait-for-startup"),
+            progress_port: progress_port(),
+        });
+    }
+
+    let result = Pages::run(Settings {
+        flags: LaunchOptions {
+            firmware: firmware_argument(),
+            wait_for_startup: has_argument("--wait-for-startup"),
+            progress_port: progress_port(),
+            skip_release_check: has_argument("--no-update-check"),
+        },
         window: window::Settings {
             size: (500, 370),
+            // The pages are laid out for the default size, so it can grow but not shrink.
+            min_size: Some((500, 370)),
             visible: true,
-            resizable: false,
+            resizable: true,
             ..Default::default()
         },
         ..Default::default()
-    })
+    });
+
+    if let Err(error) = result {
+        error!("Unable to start the updater: {}", error);
+        return ExitCode::FAILURE;
+    }
+    exit_code()
+}
+
+// Allows the updater to be launched with a firmware file (for example, by dropping it on the
+// executable), it's only accepted if it's actually a GoXLR firmware.
+fn firmware_argument() -> Option<PathBuf> {
+    let mut args = std::env::args_os().skip(1);
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        // The value after one of these belongs to it, and isn't the firmware.
+        if VALUE_ARGUMENTS.iter().any(|name| arg == *name) {
+            args.next();
+        } else if !arg.to_string_lossy().starts_with('-') {
+            path = Some(PathBuf::from(arg));
+            break;
+        }
+    }
+    let path = path?;
+
+    match firmware::check_firmware(path.clone()) {
+        Ok(_) => Some(path),
+        Err(error) => {
+            warn!("Ignoring {}: {}", path.to_string_lossy(), error);
+            None
+        }
+    }
+}
+
+fn inspect_firmware(path: &Path) {
+    let header = match firmware::load_firmware_header(path) {
+        Ok(header) => header,
+        Err(error) => {
+            println!("Unable to parse {}: {}", path.to_string_lossy(), error);
+            return;
+        }
+    };
+
+    println!("Name:    {}", header.name);
+    println!("Device:  {:?}", header.device);
+    println!("Version: {}", header.version);
+    match header.length_field {
+        Some(length) => println!("Length:  {} bytes (unconfirmed)", length),
+        None => println!("Length:  Unknown"),
+    }
+    println!("Header:");
+    for (index, line) in header.raw.chunks(16).enumerate() {
+        println!("  {:04x}: {:02x?}", index * 16, line);
+    }
+}
+
+// Arguments which are followed by a value.
+const VALUE_ARGUMENTS: &[&str] = &[
+    "--device",
+    "--download-url",
+    "--file",
+    "--inspect-firmware",
+    "--progress-port",
+];
+
+// Arguments are read with args_os, as args panics on anything which isn't valid UTF-8, and the
+// firmware path is allowed to be anything.
+fn argument_path(name: &str) -> Option<PathBuf> {
+    let mut args = std::env::args_os().skip(1);
+    args.find(|arg| arg == name)?;
+    args.next().map(PathBuf::from)
+}
+
+fn argument_value(name: &str) -> Option<String> {
+    let mut args = std::env::args_os().skip(1);
+    args.find(|arg| arg == name)?;
+    match args.next()?.into_string() {
+        Ok(value) => Some(value),
+        Err(value) => {
+            warn!("Ignoring invalid {}: {}", name, value.to_string_lossy());
+            None
+        }
+    }
+}
+
+fn has_argument(name: &str) -> bool {
+    std::env::args_os().skip(1).any(|arg| arg == name)
+}
+
+fn progress_port() -> Option<u16> {
+    let value = argument_value("--progress-port")?;
+    match value.parse() {
+        Ok(port) => Some(port),
+        Err(_) => {
+            warn!("Ignoring invalid --progress-port: {}", value);
+            None
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LaunchOptions {
+    firmware: Option<PathBuf>,
+
+    // Only needed if the GoXLR has just been powered on, and is still playing its startup
+    // animation when we try to talk to it.
+    wait_for_startup: bool,
+
+    // Publish the update's progress on this localhost port, for external tooling.
+    progress_port: Option<u16>,
+
+    // For anyone who'd rather the updater didn't check GitHub for a newer version of itself.
+    skip_release_check: bool,
 }
 
 pub struct Pages {
-    receiver: RefCell<Option<UnboundedReceiver<Message>>>,
+    receiver: Arc<tokio::sync::Mutex<UnboundedReceiver<Message>>>,
     steps: Steps,
 }
 
@@ -50,22 +256,35 @@ impl Application for Pages {
     type Executor = executor::Default;
     type Message = Message;
     type Theme = Theme;
-    type Flags = ();
+    type Flags = LaunchOptions;
 
-    fn new(_flags: ()) -> (Self, Command<Self::Message>) {
+    fn new(options: LaunchOptions) -> (Self, Command<Self::Message>) {
         let (sender, receiver) = mpsc::unbounded_channel();
-        let goxlr = Arc::new(Mutex::new(GoXLR::new(sender.clone())));
+        let receiver = monitor_progress(receiver, options.progress_port);
+        let goxlr = GoXLR::new(sender.clone(), options.wait_for_startup);
+        let goxlr = Arc::new(Mutex::new(goxlr));
+
+        if !options.skip_release_check {
+            let sender = sender.clone();
+            thread::spawn(move || check_for_release(sender));
+        }
+
+        let mut steps = Steps::new(sender, goxlr);
+        if let Some(firmware) = options.firmware {
+            steps.preselect_firmware(firmware);
+        }
+
         (
             Pages {
-                receiver: RefCell::new(Some(receiver)),
-                steps: Steps::new(sender, goxlr),
+                receiver: Arc::new(tokio::sync::Mutex::new(receiver)),
+                steps,
             },
             Command::none(),
         )
     }
 
     fn title(&self) -> String {
-        "GoXLR Firmware Updater".to_string()
+        WINDOW_TITLE.to_string()
     }
 
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
@@ -73,6 +292,14 @@ impl Application for Pages {
             Message::NextPressed => {
                 self.steps.advance();
             }
+            Message::BackPressed => {
+                self.steps.go_back();
+            }
+            Message::EscapePressed => {
+                if self.steps.escape() {
+                    return window::close();
+                }
+            }
             Message::StepsMessage(msg) => {
                 self.steps.update_steps(msg);
             }
@@ -87,8 +314,13 @@ impl Application for Pages {
         let Pages { steps, .. } = self;
 
         let mut controls = row![];
+        if steps.can_go_back() {
+            controls = controls.push(button(tr("button.back")).on_press(Message::BackPressed));
+        }
         controls = controls.push(horizontal_space(Length::Fill));
-        controls = controls.push(button("Next").on_press(Message::NextPressed));
+        if steps.can_continue() {
+            controls = controls.push(button(steps.next_label()).on_press(Message::NextPressed));
+        }
 
         let header = steps.header_text().map(Message::PageMessage);
         let ruler = Rule::horizontal(5);
@@ -100,17 +332,20 @@ impl Application for Pages {
                 top: 5.0,
                 right: 10.0,
                 bottom: 5.0,
-                left: 0.0,
+                left: 10.0,
             })
             .height(45);
 
-        let content: Element<_> = if self.steps.can_continue() {
+        let content: Element<_> = if steps.can_continue() || steps.can_go_back() {
             column![header, ruler, body, ruler2, controls].into()
         } else {
             column![header, ruler, body, blank].into()
         };
 
-        container(content).width(Length::Fill).height(370).into()
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
     }
 
     fn theme(&self) -> Self::Theme {
@@ -118,20 +353,47 @@ impl Application for Pages {
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
-        iced::subscription::unfold(
+        let external = iced::subscription::unfold(
             "External Message",
-            self.receiver.take(),
-            move |mut receiver| async move {
-                let message = receiver.as_mut().unwrap().recv().await.unwrap();
-                (message, receiver)
+            // Shared rather than taken, so if iced ever rebuilds the subscription, it picks up
+            // the same channel instead of finding nothing there.
+            self.receiver.clone(),
+            move |receiver| async move {
+                let message = receiver.lock().await.recv().await;
+                match message {
+                    Some(message) => (message, receiver),
+
+                    // Every sender has gone, so nothing else is ever going to arrive.
+                    None => iced::futures::future::pending().await,
+                }
             },
-        )
+        );
+
+        // Files dropped on the window, only the file selection step does anything with them. Keys
+        // a widget has already used (eg, Enter in a text box) are left alone.
+        let events = iced::subscription::events_with(|event, status| match (event, status) {
+            (Event::Window(window::Event::FileDropped(path)), _) => {
+                Some(Message::PageMessage(PageMessages::FirmwareDropped(path)))
+            }
+            (Event::Keyboard(keyboard::Event::KeyPressed { key_code, .. }), Status::Ignored) => {
+                match key_code {
+                    KeyCode::Enter | KeyCode::NumpadEnter => Some(Message::NextPressed),
+                    KeyCode::Escape => Some(Message::EscapePressed),
+                    _ => None,
+                }
+            }
+            _ => None,
+        });
+
+        Subscription::batch([external, events])
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     NextPressed,
+    BackPressed,
+    EscapePressed,
     StepsMessage(StepMessages),
     PageMessage(PageMessages),
 }
@@ -141,8 +403,15 @@ pub enum StepMessages {
     SetDevice(Device),
     SelectFile(Option<PathBuf>),
     SetUpdateType(SelectUpdateOption),
+    SetCheckBeforeDownload(bool),
+    SetDownloadUrl(Option<String>),
     SetFirmware(FirmwareDetails),
     ClearFirmware(),
+    SetUpdateOptions(UpdateOptions),
+    SetAllowBlockedFirmware(bool),
+    SetDryRun(bool),
+    SetBatchDevices(Vec<Device>),
+    UpdateFinished(Outcome),
 }
 
 struct Steps {
@@ -152,57 +421,144 @@ struct Steps {
 
 impl Steps {
     fn new(sender: UnboundedSender<Message>, goxlr: Arc<Mutex<GoXLR>>) -> Steps {
+        let busy = goxlr.lock().unwrap().busy_flag();
+
+        // If these exact licenses have been agreed to before, there's no need to do it again.
+        let accepted = licenses_accepted();
+
         Steps {
             steps: vec![
-                Step::Welcome,
-                Step::LicenseOne { agreed: false },
-                Step::LicenseTwo { agreed: false },
+                Step::Welcome {
+                    newer_release: None,
+                },
+                Step::LicenseOne {
+                    agreed: accepted,
+                    read: accepted,
+                },
+                Step::LicenseTwo {
+                    agreed: accepted,
+                    read: accepted,
+                },
                 Step::Status {
                     sender: sender.clone(),
                     app: false,
                     beta: false,
                     util: false,
+                    fast_startup: false,
+                    fast_startup_dismissed: false,
+                    confirm_close: false,
                 },
                 Step::LocateGoXLR {
                     goxlr: goxlr.clone(),
+                    busy,
                     sender: sender.clone(),
                     devices: None,
                     selected: None,
+                    test_result: None,
+                    update_all: false,
+                    ignored: vec![],
+                    show_ignored: false,
+                    problems: vec![],
+                    scan_cancel: Arc::new(AtomicBool::new(false)),
                 },
                 Step::UpdateMethod {
                     sender: sender.clone(),
                     selected: None,
+                    check_first: false,
+                    download_url: String::new(),
+                    options: UpdateOptions::default(),
+                    storage: None,
+                    storage_error: None,
                 },
                 Step::SelectFile {
-                    sender,
+                    sender: sender.clone(),
                     file_valid: false,
                     file: None,
                     progress: 0,
-                    downgrade: false,
+                    rate: None,
+                    source: None,
+                    download_error: None,
+                    download_cancel: Arc::new(AtomicBool::new(false)),
+                    cached: None,
+                    accept_change: false,
+                    override_blocked: false,
+                    app_version: None,
+                    app_acknowledged: false,
+                    expected_hash: String::new(),
+                    dry_run: false,
                     device: None,
                     fetch_method: None,
+                    check_first: false,
+                    download_url: None,
+                    latest: None,
+                    confirm_download: false,
+                    folder_matches: None,
                     details: None,
+                    file_error: None,
+                },
+                Step::Confirm {
+                    device: None,
+                    firmware: None,
+                    batch: vec![],
+                    options: UpdateOptions::default(),
                 },
                 Step::RunUpdate {
-                    goxlr,
+                    goxlr: goxlr.clone(),
 
                     device: None,
                     firmware: None,
-
+                    options: UpdateOptions::default(),
+                    batch: vec![],
+
+                    started: None,
+                    version_records: vec![],
+                    identity: None,
+                    completed: vec![],
+                    history: vec![],
                     stage: "Starting".to_string(),
+                    retry: None,
                     percentage: 0,
+                    rate: None,
+                    overall: 0,
                     message: None,
 
                     // Final States..
+                    awaiting_reboot: false,
                     complete: false,
                     is_error: false,
+                    write_incomplete: false,
+                    disconnected: false,
+                },
+                Step::Finish {
+                    sender,
+                    goxlr,
+                    device: None,
+                    firmware: None,
+                    options: UpdateOptions::default(),
+                    succeeded: None,
+                    elapsed: None,
+                    version_records: vec![],
+                    app_version: None,
+                    reboot_check: None,
                 },
-                Step::Finish,
             ],
             current: 0,
         }
     }
 
+    // Sets up the update method and file ahead of time, the user still goes through the rest of
+    // the wizard as normal.
+    fn preselect_firmware(&mut self, path: PathBuf) {
+        for step in &mut self.steps {
+            if let Step::UpdateMethod { selected, .. } = step {
+                selected.replace(SelectUpdateOption::File);
+            }
+        }
+
+        self.update_steps(StepMessages::SetUpdateType(SelectUpdateOption::File));
+        self.update_steps(StepMessages::SelectFile(Some(path)));
+    }
+
     fn update_steps(&mut self, msg: StepMessages) {
         match msg {
             StepMessages::SetDevice(selected_device) => {
@@ -211,7 +567,10 @@ impl Steps {
                     if let Step::SelectFile { device, .. } = step {
                         device.replace(selected_device.clone());
                     }
-                    if let Step::RunUpdate { device, .. } = step {
+                    if let Step::Confirm { device, .. } | Step::RunUpdate { device, .. } = step {
+                        device.replace(selected_device.clone());
+                    }
+                    if let Step::Finish { device, .. } = step {
                         device.replace(selected_device.clone());
                     }
                 }
@@ -223,28 +582,65 @@ impl Steps {
                     }
                 }
             }
+            StepMessages::SetCheckBeforeDownload(value) => {
+                for step in &mut self.steps {
+                    if let Step::SelectFile { check_first, .. } = step {
+                        *check_first = value;
+                    }
+                }
+            }
+            StepMessages::SetDownloadUrl(url) => {
+                for step in &mut self.steps {
+                    if let Step::SelectFile {
+                        download_url,
+                        latest,
+                        ..
+                    } = step
+                    {
+                        // A version check against a different URL doesn't tell us anything.
+                        if *download_url != url {
+                            *latest = None;
+                        }
+                        download_url.clone_from(&url);
+                    }
+                }
+            }
             StepMessages::SelectFile(selected) => {
                 for step in &mut self.steps {
                     if let Step::SelectFile {
                         file,
-                        downgrade,
+                        accept_change,
+                        override_blocked,
+                        app_acknowledged,
                         details,
+                        file_error,
                         ..
                     } = step
                     {
                         if let Some(path) = &selected {
                             file.replace(path.clone());
 
-                            // Untick the box.
-                            *downgrade = false;
-                            if let Ok(firmware) = firmware::check_firmware(path.clone()) {
-                                details.replace(FirmwareDetails {
-                                    path: path.clone(),
-                                    device_type: firmware.device,
-                                    version: firmware.version,
-                                });
-                            } else {
-                                *details = None;
+                            // Untick the boxes.
+                            *accept_change = false;
+                            *override_blocked = false;
+                            *app_acknowledged = false;
+                            match check_firmware_hashed(path) {
+                                Ok((firmware, sha256, size)) => {
+                                    set_outcome(Outcome::Incomplete);
+                                    details.replace(FirmwareDetails {
+                                        path: path.clone(),
+                                        device_type: firmware.device,
+                                        version: firmware.version,
+                                        sha256,
+                                        size,
+                                    });
+                                    *file_error = None;
+                                }
+                                Err(error) => {
+                                    set_outcome(Outcome::InvalidFirmware);
+                                    *details = None;
+                                    *file_error = Some(error);
+                                }
                             }
 
                             file.replace(path.clone());
@@ -254,18 +650,100 @@ impl Steps {
             }
             StepMessages::SetFirmware(details) => {
                 for step in &mut self.steps {
-                    if let Step::RunUpdate { firmware, .. } = step {
+                    if let Step::Confirm { firmware, .. }
+                    | Step::RunUpdate { firmware, .. }
+                    | Step::Finish { firmware, .. } = step
+                    {
                         firmware.replace(details.clone());
                     }
                 }
             }
             StepMessages::ClearFirmware() => {
                 for step in &mut self.steps {
-                    if let Step::RunUpdate { firmware, .. } = step {
+                    if let Step::Confirm { firmware, .. }
+                    | Step::RunUpdate { firmware, .. }
+                    | Step::Finish { firmware, .. } = step
+                    {
                         *firmware = None;
                     }
                 }
             }
+            StepMessages::SetUpdateOptions(update_options) => {
+                for step in &mut self.steps {
+                    if let Step::Confirm { options, .. }
+                    | Step::RunUpdate { options, .. }
+                    | Step::Finish { options, .. } = step
+                    {
+                        *options = update_options;
+                    }
+                }
+            }
+            StepMessages::SetAllowBlockedFirmware(value) => {
+                for step in &mut self.steps {
+                    if let Step::UpdateMethod { options, .. } | Step::RunUpdate { options, .. } =
+                        step
+                    {
+                        options.allow_blocked_firmware = value;
+                    }
+                }
+            }
+            StepMessages::SetDryRun(value) => {
+                for step in &mut self.steps {
+                    if let Step::UpdateMethod { options, .. }
+                    | Step::Confirm { options, .. }
+                    | Step::RunUpdate { options, .. } = step
+                    {
+                        options.dry_run = value;
+                    }
+                }
+            }
+            StepMessages::SetBatchDevices(devices) => {
+                for step in &mut self.steps {
+                    if let Step::Confirm { batch, .. } = step {
+                        batch.clone_from(&devices);
+                    }
+                    if let Step::RunUpdate { batch, .. } = step {
+                        *batch = devices
+                            .iter()
+                            .map(|device| (device.clone(), BatchStatus::Pending))
+                            .collect();
+                    }
+                }
+            }
+            StepMessages::UpdateFinished(outcome) => {
+                set_outcome(outcome);
+                let success = outcome == Outcome::Success;
+
+                // The summary needs to know how the update went, not just whether it worked.
+                let mut summary = (None, vec![]);
+                for step in &self.steps {
+                    if let Step::RunUpdate {
+                        started,
+                        version_records,
+                        ..
+                    } = step
+                    {
+                        summary = (
+                            started.map(|started| started.elapsed()),
+                            version_records.clone(),
+                        );
+                    }
+                }
+
+                for step in &mut self.steps {
+                    if let Step::Finish {
+                        succeeded,
+                        elapsed,
+                        version_records,
+                        ..
+                    } = step
+                    {
+                        *succeeded = Some(success);
+                        *elapsed = summary.0;
+                        version_records.clone_from(&summary.1);
+                    }
+                }
+            }
         }
     }
 
@@ -275,7 +753,7 @@ impl Steps {
 
     fn view(&self) -> Element<PageMessages> {
         container(self.steps[self.current].view())
-            .width(500)
+            .width(Length::Fill)
             .height(Length::Fill)
             .padding(Padding {
                 top: 10.0,
@@ -288,7 +766,7 @@ impl Steps {
 
     fn header_text(&self) -> Element<PageMessages> {
         container(self.steps[self.current].header_text())
-            .width(500)
+            .width(Length::Fill)
             .height(60)
             .padding(Padding {
                 top: 10.0,
@@ -299,8 +777,37 @@ impl Steps {
             .into()
     }
 
+    // Esc stops a download that's running, otherwise it closes the updater, as long as that won't
+    // leave the GoXLR part way through an update. Returns true if we should close.
+    fn escape(&mut self) -> bool {
+        match &self.steps[self.current] {
+            Step::SelectFile {
+                fetch_method: Some(SelectUpdateOption::Download),
+                file: None,
+                download_error: None,
+                confirm_download: false,
+                ..
+            } => {
+                self.update(PageMessages::CancelDownload);
+                false
+            }
+            Step::RunUpdate {
+                complete,
+                awaiting_reboot,
+                write_incomplete,
+                ..
+            } => *complete && !*awaiting_reboot && !*write_incomplete,
+            _ => true,
+        }
+    }
+
     fn advance(&mut self) {
         if self.can_continue() {
+            // Both licenses have been agreed to by the time we leave the second one.
+            if matches!(self.steps[self.current], Step::LicenseTwo { .. }) {
+                save_license_acceptance();
+            }
+
             self.current += 1;
             self.steps[self.current].pre_display();
         }
@@ -309,6 +816,27 @@ impl Steps {
     fn can_continue(&self) -> bool {
         self.current + 1 < self.steps.len() && self.steps[self.current].can_continue()
     }
+
+    fn next_label(&self) -> &str {
+        self.steps[self.current].next_label()
+    }
+
+    fn go_back(&mut self) {
+        if self.can_go_back() {
+            self.steps[self.current].leave();
+            self.current -= 1;
+
+            // The environment check moves on by itself, so landing on it would just bounce us
+            // straight forward again. It's re-run on the way back through.
+            if matches!(self.steps[self.current], Step::Status { .. }) {
+                self.current -= 1;
+            }
+        }
+    }
+
+    fn can_go_back(&self) -> bool {
+        self.current > 0 && self.steps[self.current].can_go_back()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -316,6 +844,8 @@ pub struct FirmwareDetails {
     path: PathBuf,
     device_type: DeviceType,
     version: VersionNumber,
+    sha256: String,
+    size: u64,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -326,38 +856,88 @@ pub enum DeviceType {
 }
 
 enum Step {
-    Welcome,
+    Welcome {
+        newer_release: Option<String>,
+    },
     LicenseOne {
         agreed: bool,
+        read: bool,
     },
     LicenseTwo {
         agreed: bool,
+        read: bool,
     },
     Status {
         sender: UnboundedSender<Message>,
         app: bool,
         beta: bool,
         util: bool,
+        fast_startup: bool,
+        fast_startup_dismissed: bool,
+        confirm_close: bool,
     },
     LocateGoXLR {
         sender: UnboundedSender<Message>,
         goxlr: Arc<Mutex<GoXLR>>,
+
+        // Set while an update is running, when nothing else should be sent to a device.
+        busy: Arc<AtomicBool>,
         devices: Option<Vec<Device>>,
         selected: Option<usize>,
+        test_result: Option<String>,
+        update_all: bool,
+        ignored: Vec<String>,
+        show_ignored: bool,
+        problems: Vec<String>,
+        scan_cancel: Arc<AtomicBool>,
     },
     UpdateMethod {
         sender: UnboundedSender<Message>,
         selected: Option<SelectUpdateOption>,
+        check_first: bool,
+        download_url: String,
+        options: UpdateOptions,
+
+        // Previously downloaded firmware, only populated while the user is looking at it.
+        storage: Option<Vec<(PathBuf, FirmwareInfo, u64)>>,
+        storage_error: Option<String>,
     },
     SelectFile {
         sender: UnboundedSender<Message>,
         file_valid: bool,
         device: Option<Device>,
         fetch_method: Option<SelectUpdateOption>,
+        check_first: bool,
+
+        // A specific firmware to download, rather than the latest.
+        download_url: Option<String>,
+        latest: Option<FirmwareInfo>,
+        confirm_download: bool,
+        folder_matches: Option<Vec<(PathBuf, FirmwareInfo)>>,
         progress: u8,
+        rate: Option<TransferStats>,
+        source: Option<String>,
+        download_error: Option<String>,
+        download_cancel: Arc<AtomicBool>,
+
+        // Set when the server couldn't be reached, and an earlier download is being used instead.
+        cached: Option<FirmwareInfo>,
         file: Option<PathBuf>,
         details: Option<FirmwareDetails>,
-        downgrade: bool,
+        file_error: Option<String>,
+        // Anything other than an upgrade has to be confirmed, whether a reinstall or a downgrade.
+        accept_change: bool,
+        override_blocked: bool,
+        app_version: Option<String>,
+        app_acknowledged: bool,
+        expected_hash: String,
+        dry_run: bool,
+    },
+    Confirm {
+        device: Option<Device>,
+        firmware: Option<FirmwareDetails>,
+        batch: Vec<Device>,
+        options: UpdateOptions,
     },
     RunUpdate {
         goxlr: Arc<Mutex<GoXLR>>,
@@ -365,17 +945,43 @@ enum Step {
         // Ok, we need the device and firmware details..
         device: Option<Device>,
         firmware: Option<FirmwareDetails>,
+        options: UpdateOptions,
+
+        // If we're updating several devices, each one and how it's getting on..
+        batch: Vec<(Device, BatchStatus)>,
 
         // State Tracking..
+        started: Option<Instant>,
+        version_records: Vec<PathBuf>,
+        identity: Option<(u64, String)>,
+        completed: Vec<String>,
+        history: Vec<String>,
         stage: String,
+        retry: Option<String>,
         percentage: u8,
+        rate: Option<TransferStats>,
+        overall: u8,
         message: Option<String>,
 
         // We're done.
+        awaiting_reboot: bool,
         complete: bool,
         is_error: bool,
+        write_incomplete: bool,
+        disconnected: bool,
+    },
+    Finish {
+        sender: UnboundedSender<Message>,
+        goxlr: Arc<Mutex<GoXLR>>,
+        device: Option<Device>,
+        firmware: Option<FirmwareDetails>,
+        options: UpdateOptions,
+        succeeded: Option<bool>,
+        elapsed: Option<Duration>,
+        version_records: Vec<PathBuf>,
+        app_version: Option<String>,
+        reboot_check: Option<Result<VersionNumber, String>>,
     },
-    Finish,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -388,44 +994,129 @@ pub enum SelectUpdateOption {
 pub enum PageMessages {
     NoneBool(bool),
 
+    SelectLanguage(Language),
+    NewerReleaseAvailable(String),
+    OpenReleasePage,
+
     ToggleAcceptLicenseOne(bool),
     ToggleAcceptLicenseTwo(bool),
+    LicenseScrolled(f32),
     UpdateStatusCheck(bool, bool, bool),
+    DismissFastStartup(bool),
+    ConfirmCloseApps(bool),
+    CloseApps,
     UpdateDeviceList(Vec<Device>),
+    UpdateIgnoredDevices(Vec<String>),
+    UpdateProblemDevices(Vec<String>),
+    ToggleIgnoredDevices,
     SelectFirmwareOption(SelectUpdateOption),
+    SetCheckBeforeDownload(bool),
+    SetDownloadUrl(String),
+    SetPauseBeforeReboot(bool),
+    SetStayInUpdateMode(bool),
+    SetCheckAfterReboot(bool),
+    ToggleStorage,
+    ClearStorage,
+    OpenStorageFolder,
+    SelectDownloadFolder,
     SelectDevice(usize),
+    CancelDeviceScan,
+    RescanDevices,
+    TestDevice,
+    DeviceTestResult(String),
+    SetUpdateAll(bool),
 
     SelectFirmware,
+    FirmwareDropped(PathBuf),
+    SelectFirmwareFolder,
+    FirmwareFolderScanned(Vec<(PathBuf, FirmwareInfo)>),
+    SelectFolderFirmware(usize),
+    LatestFirmwareChecked(FirmwareInfo),
+    StartDownload,
     DownloadFirmwarePercent(u8),
-
-    SetAcceptDowngrade(bool),
+    DownloadFirmwareRate(Option<TransferStats>),
+    DownloadFirmwareSource(String),
+    CancelDownload,
+    DownloadFirmwareError(String),
+    UsingCachedFirmware(FirmwareInfo),
+    RetryDownload,
+    UseFileInstead,
+
+    SetAcceptVersionChange(bool),
+    SetOverrideBlocked(bool),
+    SetAcknowledgeAppVersion(bool),
+    SetExpectedHash(String),
+    SetDryRun(bool),
     SetFirmwareValid(bool),
 
     // Actual Firmware Details
+    UpdateFirmwareIdentity(u64, String),
+    UpdateFirmwareVersionRecord(PathBuf),
     UpdateFirmwareStage(String),
+    UpdateFirmwareRetry(Option<String>),
     UpdateFirmwarePercent(u8),
+    UpdateFirmwareRate(Option<TransferStats>),
+    UpdateFirmwareOverallPercent(u8),
     UpdateFirmwareMessage(String),
     UpdateFirmwareComplete(bool),
     UpdateFirmwareIsError(bool),
+    UpdateFirmwareAwaitingReboot(bool),
+    UpdateFirmwareWriteIncomplete(bool),
+    UpdateFirmwareDisconnected(bool),
+    RetryUpdate,
+    SaveUpdateLog,
+    UpdateBatchStatus(usize, BatchStatus),
+    RebootDevice,
+
+    InstalledAppVersion(Option<String>),
+    RebootCheckResult(Result<VersionNumber, String>),
 }
 
 impl<'a> Step {
     fn pre_display(&mut self) {
-        if let Step::Status { sender, .. } = self {
+        if let Step::Status {
+            sender,
+            fast_startup,
+            ..
+        } = self
+        {
+            // This is only a quick registry check, so do it here, before we can auto-advance.
+            *fast_startup = fast_startup_enabled();
+
             // Spawn the thread that monitors to make sure everything is shut down..
             let sender = sender.clone();
             thread::spawn(move || status_check(sender));
         }
 
-        if let Step::LocateGoXLR { goxlr, .. } = self {
+        if let Step::LocateGoXLR {
+            goxlr, scan_cancel, ..
+        } = self
+        {
+            scan_cancel.store(false, std::sync::atomic::Ordering::Relaxed);
+
             let clone = goxlr.clone();
-            thread::spawn(move || clone.lock().unwrap().find_devices());
+            let cancel = scan_cancel.clone();
+            thread::spawn(move || clone.lock().unwrap().find_devices(cancel));
+        }
+
+        if let Step::SelectFile { sender, .. } = self {
+            // Needed to warn about firmware the installed App is too old for.
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let version = installed_app_version();
+                let message = PageMessages::InstalledAppVersion(version);
+                let _ = sender.send(Message::PageMessage(message));
+            });
         }
 
         if let Step::SelectFile {
             sender,
             device,
             fetch_method: Some(method),
+            check_first,
+            download_url,
+            latest,
+            download_cancel,
             ..
         } = self
         {
@@ -433,7 +1124,18 @@ impl<'a> Step {
                 if let Some(device) = device {
                     let sender = sender.clone();
                     let device_type = device.device_type;
-                    thread::spawn(move || download_firmware(sender, device_type));
+                    let url = download_url.clone();
+
+                    // If requested, work out whether it's worth downloading before we do it.
+                    if *check_first && latest.is_none() {
+                        thread::spawn(move || check_latest_firmware(sender, device_type, url));
+                    } else {
+                        // Each download gets its own flag, so cancelling an old one can't stop
+                        // a new one.
+                        let cancel = Arc::new(AtomicBool::new(false));
+                        *download_cancel = cancel.clone();
+                        thread::spawn(move || download_firmware(sender, device_type, url, cancel));
+                    }
                 }
             }
         }
@@ -442,10 +1144,35 @@ impl<'a> Step {
             goxlr,
             device,
             firmware,
+            options,
+            batch,
+            started,
+            version_records,
+            history,
             ..
         } = self
         {
-            println!(
+            *started = Some(Instant::now());
+            version_records.clear();
+
+            // Retrying keeps what's already there, the earlier attempt is often the useful bit.
+            history.push(String::from("Starting update"));
+            if let Some(device) = device {
+                history.push(format!(
+                    "Device: GoXLR {:?} ({}), running firmware {}",
+                    device.device_type, device.device_serial, device.version
+                ));
+            }
+            if let Some(firmware) = firmware {
+                history.push(format!(
+                    "Target: GoXLR {:?} firmware {}, from {}",
+                    firmware.device_type,
+                    firmware.version,
+                    firmware.path.to_string_lossy()
+                ));
+            }
+
+            info!(
                 "Starting Firmware Update for: {:?}, with {:?}",
                 device, firmware
             );
@@ -453,14 +1180,51 @@ impl<'a> Step {
             // Grab a useful reference to our GoXLR object..
             let g = goxlr.clone();
 
-            // Ok, we know at this point that these values have been definitively set, it's not
-            // possible to progress out of the previous steps without triggering the code that sets
-            // them, so we're safe to flat .unwrap here.
-            let d = device.as_ref().unwrap().clone();
-            let f = firmware.as_ref().unwrap().clone();
+            // The previous steps shouldn't let us get here without these being set, but if they
+            // somehow haven't been, fail the update rather than the whole app.
+            let (d, f) = match (device, firmware) {
+                (Some(device), Some(firmware)) => (device.clone(), firmware.clone()),
+                _ => {
+                    let error = "Internal Error: Device or Firmware not selected";
+                    thread::spawn(move || g.lock().unwrap().send_setup_error(error));
+                    return;
+                }
+            };
+            let o = *options;
 
             // Spawn the update thread, and hope for the best :D
-            thread::spawn(move || g.lock().unwrap().do_update(d, f));
+            if batch.is_empty() {
+                thread::spawn(move || g.lock().unwrap().do_update(d, f, o));
+            } else {
+                let devices = batch.iter().map(|(device, _)| device.clone()).collect();
+                thread::spawn(move || g.lock().unwrap().do_batch_update(devices, f, o));
+            }
+        }
+
+        if let Step::Finish { sender, .. } = self {
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let version = installed_app_version();
+                let message = PageMessages::InstalledAppVersion(version);
+                let _ = sender.send(Message::PageMessage(message));
+            });
+        }
+
+        if let Step::Finish {
+            goxlr,
+            device: Some(device),
+            options,
+            succeeded: Some(true),
+            reboot_check,
+            ..
+        } = self
+        {
+            if options.check_after_reboot && !options.dry_run {
+                *reboot_check = None;
+                let g = goxlr.clone();
+                let d = device.clone();
+                thread::spawn(move || g.lock().unwrap().check_rebooted_device(d));
+            }
         }
     }
 
@@ -468,15 +1232,23 @@ impl<'a> Step {
         match msg {
             PageMessages::NoneBool(_) => {}
             PageMessages::ToggleAcceptLicenseOne(value) => {
-                if let Step::LicenseOne { agreed } = self {
+                if let Step::LicenseOne { agreed, .. } = self {
                     *agreed = value;
                 }
             }
             PageMessages::ToggleAcceptLicenseTwo(value) => {
-                if let Step::LicenseTwo { agreed } = self {
+                if let Step::LicenseTwo { agreed, .. } = self {
                     *agreed = value
                 }
             }
+            PageMessages::LicenseScrolled(offset) => {
+                if let Step::LicenseOne { read, .. } | Step::LicenseTwo { read, .. } = self {
+                    // Close enough to the bottom to count as having been read.
+                    if offset >= 0.99 {
+                        *read = true;
+                    }
+                }
+            }
             PageMessages::UpdateStatusCheck(app_running, beta_running, util_running) => {
                 if let Step::Status {
                     app, beta, util, ..
@@ -487,20 +1259,62 @@ impl<'a> Step {
                     *util = !util_running;
                 }
             }
+            PageMessages::DismissFastStartup(value) => {
+                if let Step::Status {
+                    fast_startup_dismissed,
+                    ..
+                } = self
+                {
+                    *fast_startup_dismissed = value;
+                }
+            }
+            PageMessages::ConfirmCloseApps(value) => {
+                if let Step::Status { confirm_close, .. } = self {
+                    *confirm_close = value;
+                }
+            }
+            PageMessages::CloseApps => {
+                if let Step::Status { confirm_close, .. } = self {
+                    *confirm_close = false;
+                }
+
+                // The status check is still running, so will notice once they've gone.
+                thread::spawn(close_apps);
+            }
             PageMessages::UpdateDeviceList(list) => {
+                if list.is_empty() {
+                    set_outcome(Outcome::DeviceNotFound);
+                }
                 if let Step::LocateGoXLR { devices, .. } = self {
                     *devices = Some(list);
                 }
             }
+            PageMessages::UpdateIgnoredDevices(list) => {
+                if let Step::LocateGoXLR { ignored, .. } = self {
+                    *ignored = list;
+                }
+            }
+            PageMessages::UpdateProblemDevices(list) => {
+                if let Step::LocateGoXLR { problems, .. } = self {
+                    *problems = list;
+                }
+            }
+            PageMessages::ToggleIgnoredDevices => {
+                if let Step::LocateGoXLR { show_ignored, .. } = self {
+                    *show_ignored = !*show_ignored;
+                }
+            }
             PageMessages::SelectDevice(device) => {
                 if let Step::LocateGoXLR {
                     sender,
                     selected,
                     devices,
+                    test_result,
                     ..
                 } = self
                 {
                     *selected = Some(device);
+                    *test_result = None;
 
                     // Send the selection upstream for the next page.
                     if let Some(devices) = devices {
@@ -510,17 +1324,300 @@ impl<'a> Step {
                     }
                 }
             }
-            PageMessages::SelectFirmwareOption(method) => {
-                if let Step::UpdateMethod { sender, selected } = self {
-                    *selected = Some(method);
-                    let _ = sender.send(Message::StepsMessage(StepMessages::SetUpdateType(method)));
+            PageMessages::CancelDeviceScan => {
+                if let Step::LocateGoXLR { scan_cancel, .. } = self {
+                    scan_cancel.store(true, std::sync::atomic::Ordering::Relaxed);
                 }
             }
+            PageMessages::RescanDevices => {
+                if let Step::LocateGoXLR {
+                    busy, test_result, ..
+                } = self
+                {
+                    if busy.load(std::sync::atomic::Ordering::Relaxed) {
+                        *test_result = Some(String::from(BUSY_MESSAGE));
+                        return;
+                    }
+                }
 
-            PageMessages::DownloadFirmwarePercent(percent) => {
-                if let Step::SelectFile { progress, .. } = self {
-                    *progress = percent
+                if let Step::LocateGoXLR {
+                    sender,
+                    devices,
+                    selected,
+                    test_result,
+                    ignored,
+                    problems,
+                    update_all,
+                    ..
+                } = self
+                {
+                    *devices = None;
+                    *selected = None;
+                    *test_result = None;
+                    ignored.clear();
+                    problems.clear();
+
+                    // The old batch may include devices which have since gone, so it's chosen
+                    // again from the new list.
+                    *update_all = false;
+                    let batch = StepMessages::SetBatchDevices(vec![]);
+                    let _ = sender.send(Message::StepsMessage(batch));
                 }
+
+                // Re-running the pre-display will start a fresh scan.
+                self.pre_display();
+            }
+            PageMessages::TestDevice => {
+                if let Step::LocateGoXLR {
+                    goxlr,
+                    devices: Some(devices),
+                    selected: Some(selected),
+                    test_result,
+                    busy,
+                    ..
+                } = self
+                {
+                    if busy.load(std::sync::atomic::Ordering::Relaxed) {
+                        *test_result = Some(String::from(BUSY_MESSAGE));
+                        return;
+                    }
+                    *test_result = Some(String::from("Testing.."));
+
+                    let g = goxlr.clone();
+                    let d = devices[*selected].clone();
+                    thread::spawn(move || g.lock().unwrap().test_device(d));
+                }
+            }
+            PageMessages::DeviceTestResult(result) => {
+                if let Step::LocateGoXLR { test_result, .. } = self {
+                    *test_result = Some(result);
+                }
+            }
+            PageMessages::SetUpdateAll(value) => {
+                if let Step::LocateGoXLR {
+                    sender,
+                    devices: Some(devices),
+                    update_all,
+                    ..
+                } = self
+                {
+                    *update_all = value;
+
+                    let batch = if value { devices.clone() } else { vec![] };
+                    let _ =
+                        sender.send(Message::StepsMessage(StepMessages::SetBatchDevices(batch)));
+                }
+            }
+            PageMessages::SelectFirmwareOption(method) => {
+                if let Step::UpdateMethod {
+                    sender, selected, ..
+                } = self
+                {
+                    *selected = Some(method);
+                    let _ = sender.send(Message::StepsMessage(StepMessages::SetUpdateType(method)));
+                }
+            }
+            PageMessages::SetCheckBeforeDownload(value) => {
+                if let Step::UpdateMethod {
+                    sender,
+                    check_first,
+                    ..
+                } = self
+                {
+                    *check_first = value;
+                    let _ = sender.send(Message::StepsMessage(
+                        StepMessages::SetCheckBeforeDownload(value),
+                    ));
+                }
+            }
+            PageMessages::SetDownloadUrl(value) => {
+                if let Step::UpdateMethod {
+                    sender,
+                    download_url,
+                    ..
+                } = self
+                {
+                    // Only a usable URL is passed on, the page won't continue with anything else.
+                    let url = Some(value.trim().to_string())
+                        .filter(|url| !url.is_empty() && check_download_url(url).is_ok());
+                    *download_url = value;
+                    let _ = sender.send(Message::StepsMessage(StepMessages::SetDownloadUrl(url)));
+                }
+            }
+            PageMessages::SetPauseBeforeReboot(value) => {
+                if let Step::UpdateMethod {
+                    sender, options, ..
+                } = self
+                {
+                    options.pause_before_reboot = value;
+                    let _ = sender.send(Message::StepsMessage(StepMessages::SetUpdateOptions(
+                        *options,
+                    )));
+                }
+            }
+            PageMessages::SetStayInUpdateMode(value) => {
+                if let Step::UpdateMethod {
+                    sender, options, ..
+                } = self
+                {
+                    options.stay_in_update_mode_on_failure = value;
+                    let _ = sender.send(Message::StepsMessage(StepMessages::SetUpdateOptions(
+                        *options,
+                    )));
+                }
+            }
+            PageMessages::SetCheckAfterReboot(value) => {
+                if let Step::UpdateMethod {
+                    sender, options, ..
+                } = self
+                {
+                    options.check_after_reboot = value;
+                    let _ = sender.send(Message::StepsMessage(StepMessages::SetUpdateOptions(
+                        *options,
+                    )));
+                }
+            }
+            PageMessages::ToggleStorage => {
+                if let Step::UpdateMethod {
+                    storage,
+                    storage_error,
+                    ..
+                } = self
+                {
+                    *storage = match storage {
+                        Some(_) => None,
+                        None => Some(stored_firmware()),
+                    };
+                    *storage_error = None;
+                }
+            }
+            PageMessages::ClearStorage => {
+                if let Step::UpdateMethod {
+                    storage,
+                    storage_error,
+                    ..
+                } = self
+                {
+                    *storage_error = clear_stored_firmware().err();
+                    *storage = Some(stored_firmware());
+                }
+            }
+            PageMessages::OpenStorageFolder => {
+                thread::spawn(open_download_dir);
+            }
+            PageMessages::SelectDownloadFolder => {
+                if let Some(folder) = FileDialog::new()
+                    .set_directory(download_dir())
+                    .pick_folder()
+                {
+                    set_download_dir(folder);
+                }
+            }
+
+            PageMessages::LatestFirmwareChecked(info) => {
+                if let Step::SelectFile {
+                    latest,
+                    confirm_download,
+                    ..
+                } = self
+                {
+                    *latest = Some(info);
+                    *confirm_download = true;
+                }
+            }
+            PageMessages::StartDownload => {
+                if let Step::SelectFile {
+                    confirm_download, ..
+                } = self
+                {
+                    *confirm_download = false;
+                }
+
+                // With the version check done, this will now start the actual download.
+                self.pre_display();
+            }
+            PageMessages::CancelDownload => {
+                if let Step::SelectFile {
+                    sender,
+                    download_cancel,
+                    ..
+                } = self
+                {
+                    // The download cleans up after itself, send the user back to pick another way.
+                    download_cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+                    let _ = sender.send(Message::BackPressed);
+                }
+            }
+            PageMessages::DownloadFirmwarePercent(percent) => {
+                if let Step::SelectFile { progress, .. } = self {
+                    *progress = percent
+                }
+
+                if percent < 100 {
+                    set_taskbar_progress(TaskbarProgress::Normal(percent));
+                } else {
+                    set_taskbar_progress(TaskbarProgress::None);
+                }
+            }
+            PageMessages::DownloadFirmwareRate(value) => {
+                if let Step::SelectFile { rate, .. } = self {
+                    *rate = value;
+                }
+            }
+            PageMessages::DownloadFirmwareSource(url) => {
+                if let Step::SelectFile { source, .. } = self {
+                    *source = Some(url);
+                }
+            }
+            PageMessages::DownloadFirmwareError(error) => {
+                set_outcome(Outcome::DownloadFailed);
+                if let Step::SelectFile { download_error, .. } = self {
+                    *download_error = Some(error);
+                }
+                set_taskbar_progress(TaskbarProgress::Error);
+            }
+            PageMessages::UsingCachedFirmware(info) => {
+                if let Step::SelectFile { cached, .. } = self {
+                    *cached = Some(info);
+                }
+                set_taskbar_progress(TaskbarProgress::None);
+            }
+            PageMessages::RetryDownload => {
+                if let Step::SelectFile {
+                    progress,
+                    rate,
+                    source,
+                    download_error,
+                    ..
+                } = self
+                {
+                    *progress = 0;
+                    *rate = None;
+                    *source = None;
+                    *download_error = None;
+                }
+                set_taskbar_progress(TaskbarProgress::None);
+
+                // Re-running the pre-display will kick off a fresh download.
+                self.pre_display();
+            }
+            PageMessages::UseFileInstead => {
+                if let Step::SelectFile {
+                    fetch_method,
+                    progress,
+                    rate,
+                    source,
+                    download_error,
+                    ..
+                } = self
+                {
+                    *fetch_method = Some(SelectUpdateOption::File);
+                    *progress = 0;
+                    *rate = None;
+                    *source = None;
+                    *download_error = None;
+                }
+                set_taskbar_progress(TaskbarProgress::None);
             }
 
             PageMessages::SelectFirmware => {
@@ -536,10 +1633,97 @@ impl<'a> Step {
                     }
                 }
             }
+            PageMessages::FirmwareDropped(path) => {
+                // Dropping a file shouldn't replace one that's being downloaded.
+                if let Step::SelectFile {
+                    sender,
+                    fetch_method: Some(SelectUpdateOption::File),
+                    ..
+                } = self
+                {
+                    info!("Firmware dropped on window: {}", path.to_string_lossy());
+                    let message = StepMessages::SelectFile(Some(path));
+                    let _ = sender.send(Message::StepsMessage(message));
+                }
+            }
+            PageMessages::SelectFirmwareFolder => {
+                if let Step::SelectFile {
+                    sender,
+                    device: Some(device),
+                    ..
+                } = self
+                {
+                    if let Some(folder) = FileDialog::new().set_directory("/").pick_folder() {
+                        let sender = sender.clone();
+                        let device_type = device.device_type;
+
+                        // Parsing a big folder of files can take a moment, so do it off-thread.
+                        thread::spawn(move || {
+                            let found = firmware::find_firmware_files(&folder, device_type);
+                            let message = PageMessages::FirmwareFolderScanned(found);
+                            let _ = sender.send(Message::PageMessage(message));
+                        });
+                    }
+                }
+            }
+            PageMessages::FirmwareFolderScanned(found) => {
+                if let Step::SelectFile { folder_matches, .. } = self {
+                    *folder_matches = Some(found);
+                }
+            }
+            PageMessages::SelectFolderFirmware(index) => {
+                if let Step::SelectFile {
+                    sender,
+                    folder_matches: Some(matches),
+                    ..
+                } = self
+                {
+                    if let Some((path, _)) = matches.get(index) {
+                        let _ = sender.send(Message::StepsMessage(StepMessages::SelectFile(Some(
+                            path.clone(),
+                        ))));
+                    }
+                }
+            }
 
-            PageMessages::SetAcceptDowngrade(value) => {
-                if let Step::SelectFile { downgrade, .. } = self {
-                    *downgrade = value
+            PageMessages::SetAcceptVersionChange(value) => {
+                if let Step::SelectFile { accept_change, .. } = self {
+                    *accept_change = value
+                }
+            }
+            PageMessages::SetOverrideBlocked(value) => {
+                if let Step::SelectFile {
+                    sender,
+                    override_blocked,
+                    ..
+                } = self
+                {
+                    *override_blocked = value;
+                    let _ = sender.send(Message::StepsMessage(
+                        StepMessages::SetAllowBlockedFirmware(value),
+                    ));
+                }
+            }
+            PageMessages::SetDryRun(value) => {
+                if let Step::SelectFile {
+                    sender, dry_run, ..
+                } = self
+                {
+                    *dry_run = value;
+                    let _ = sender.send(Message::StepsMessage(StepMessages::SetDryRun(value)));
+                }
+            }
+            PageMessages::SetAcknowledgeAppVersion(value) => {
+                if let Step::SelectFile {
+                    app_acknowledged, ..
+                } = self
+                {
+                    *app_acknowledged = value;
+                }
+            }
+            PageMessages::SetExpectedHash(value) => {
+                if let Step::SelectFile { expected_hash, .. } = self {
+                    *expected_hash = value;
                 }
             }
             PageMessages::SetFirmwareValid(value) => {
@@ -547,9 +1731,46 @@ impl<'a> Step {
                     *file_valid = value;
                 }
             }
+            PageMessages::UpdateFirmwareVersionRecord(path) => {
+                if let Step::RunUpdate {
+                    version_records, ..
+                } = self
+                {
+                    version_records.push(path);
+                }
+            }
+            PageMessages::UpdateFirmwareIdentity(size, hash) => {
+                if let Step::RunUpdate { identity, .. } = self {
+                    *identity = Some((size, hash));
+                }
+            }
             PageMessages::UpdateFirmwareStage(value) => {
-                if let Step::RunUpdate { stage, .. } = self {
-                    *stage = value;
+                if let Step::RunUpdate {
+                    completed,
+                    stage,
+                    retry,
+                    rate,
+                    ..
+                } = self
+                {
+                    // A new stage means the previous one finished, so keep it in the log.
+                    if *stage != value {
+                        if stage != "Starting" {
+                            completed.push(stage.clone());
+                        }
+                        *retry = None;
+                        *rate = None;
+                    }
+                    *stage = value.clone();
+                }
+                self.record(format!("Stage: {}", value));
+            }
+            PageMessages::UpdateFirmwareRetry(value) => {
+                if let Some(value) = &value {
+                    self.record(format!("Retry: {}", value));
+                }
+                if let Step::RunUpdate { retry, .. } = self {
+                    *retry = value;
                 }
             }
             PageMessages::UpdateFirmwarePercent(value) => {
@@ -557,131 +1778,524 @@ impl<'a> Step {
                     *percentage = value;
                 }
             }
+            PageMessages::UpdateFirmwareRate(value) => {
+                if let Step::RunUpdate { rate, .. } = self {
+                    *rate = value;
+                }
+            }
+            PageMessages::UpdateFirmwareOverallPercent(value) => {
+                if let Step::RunUpdate { overall, .. } = self {
+                    *overall = value;
+                }
+                set_taskbar_progress(TaskbarProgress::Normal(value));
+            }
             PageMessages::UpdateFirmwareMessage(value) => {
+                self.record(format!("Message: {}", value));
                 if let Step::RunUpdate { message, .. } = self {
                     *message = Some(value);
                 }
             }
             PageMessages::UpdateFirmwareComplete(value) => {
-                if let Step::RunUpdate { complete, .. } = self {
+                if let Step::RunUpdate {
+                    complete, is_error, ..
+                } = self
+                {
                     *complete = value;
+
+                    // Leave an error showing, so it's obvious something went wrong.
+                    if value && !*is_error {
+                        set_taskbar_progress(TaskbarProgress::None);
+                    }
                 }
             }
             PageMessages::UpdateFirmwareIsError(value) => {
                 if let Step::RunUpdate { is_error, .. } = self {
                     *is_error = value;
                 }
+                if value {
+                    self.record(String::from("Update failed"));
+                    set_taskbar_progress(TaskbarProgress::Error);
+                }
+            }
+            PageMessages::UpdateFirmwareAwaitingReboot(value) => {
+                if let Step::RunUpdate {
+                    awaiting_reboot, ..
+                } = self
+                {
+                    *awaiting_reboot = value;
+                }
+                if value {
+                    set_taskbar_progress(TaskbarProgress::Paused);
+                }
+            }
+            PageMessages::UpdateFirmwareWriteIncomplete(value) => {
+                if let Step::RunUpdate {
+                    write_incomplete, ..
+                } = self
+                {
+                    *write_incomplete = value;
+                }
+                if value {
+                    self.record(String::from(
+                        "Firmware write incomplete, left in update mode",
+                    ));
+                }
+            }
+            PageMessages::UpdateFirmwareDisconnected(value) => {
+                if let Step::RunUpdate { disconnected, .. } = self {
+                    *disconnected = value;
+                }
+                if value {
+                    self.record(String::from("GoXLR disconnected"));
+                }
+            }
+            PageMessages::RetryUpdate => {
+                if let Step::RunUpdate {
+                    batch,
+                    completed,
+                    stage,
+                    retry,
+                    percentage,
+                    rate,
+                    overall,
+                    message,
+                    complete,
+                    is_error,
+                    write_incomplete,
+                    disconnected,
+                    ..
+                } = self
+                {
+                    for (_, status) in batch.iter_mut() {
+                        *status = BatchStatus::Pending;
+                    }
+                    completed.clear();
+                    *retry = None;
+                    *stage = "Starting".to_string();
+                    *percentage = 0;
+                    *rate = None;
+                    *overall = 0;
+                    *message = None;
+                    *complete = false;
+                    *is_error = false;
+                    *write_incomplete = false;
+                    *disconnected = false;
+                }
+
+                // Re-running the pre-display will start the update again from the beginning.
+                self.record(String::from("Retrying the update"));
+                self.pre_display();
+            }
+            PageMessages::SelectLanguage(language) => set_language(language),
+            PageMessages::NewerReleaseAvailable(tag) => {
+                if let Step::Welcome { newer_release } = self {
+                    *newer_release = Some(tag);
+                }
+            }
+            PageMessages::OpenReleasePage => {
+                if let Step::Welcome {
+                    newer_release: Some(tag),
+                } = self
+                {
+                    let tag = tag.clone();
+                    thread::spawn(move || open_release_page(&tag));
+                }
+            }
+            PageMessages::SaveUpdateLog => {
+                if let Step::RunUpdate { history, .. } = self {
+                    let path = FileDialog::new()
+                        .set_file_name("goxlr-update-log.txt")
+                        .save_file();
+                    if let Some(path) = path {
+                        let mut log = history.join("\n");
+                        log.push('\n');
+                        if let Err(error) = std::fs::write(&path, log) {
+                            warn!(
+                                "Unable to save log to {}: {}",
+                                path.to_string_lossy(),
+                                error
+                            );
+                        }
+                    }
+                }
+            }
+            PageMessages::UpdateBatchStatus(index, status) => {
+                if let Step::RunUpdate { batch, .. } = self {
+                    if let Some((_, current)) = batch.get_mut(index) {
+                        *current = status;
+                    }
+                }
+            }
+            PageMessages::RebootDevice => {
+                if let Step::RunUpdate {
+                    goxlr,
+                    device: Some(device),
+                    awaiting_reboot,
+                    ..
+                } = self
+                {
+                    *awaiting_reboot = false;
+
+                    let g = goxlr.clone();
+                    let d = device.clone();
+                    thread::spawn(move || g.lock().unwrap().reboot_device(d));
+                }
             }
+
+            PageMessages::InstalledAppVersion(version) => {
+                if let Step::SelectFile { app_version, .. } | Step::Finish { app_version, .. } =
+                    self
+                {
+                    *app_version = version;
+                }
+            }
+            PageMessages::RebootCheckResult(result) => {
+                if let Step::Finish { reboot_check, .. } = self {
+                    *reboot_check = Some(result);
+                }
+            }
+        }
+    }
+
+    // Keeps a record of how the update went, so it can be saved if something goes wrong.
+    fn record(&mut self, line: String) {
+        if let Step::RunUpdate {
+            started, history, ..
+        } = self
+        {
+            let elapsed = started.map(|started| started.elapsed()).unwrap_or_default();
+            history.push(format!("[{:>7.1}s] {}", elapsed.as_secs_f32(), line));
         }
     }
 
     fn title(&self) -> &str {
         match self {
-            Step::Welcome => "Welcome",
-            Step::LicenseOne { .. } => "MIT License Agreement",
-            Step::LicenseTwo { .. } => "TC-Helicon License Agreement",
-            Step::Status { .. } => "Checking Environment",
-            Step::LocateGoXLR { .. } => "Locating GoXLRs",
-            Step::UpdateMethod { .. } => "Select Update Method",
-            Step::SelectFile { fetch_method: Some(method), file, .. } => match method {
+            Step::Welcome { .. } => tr("title.welcome"),
+            Step::LicenseOne { .. } => tr("title.license-one"),
+            Step::LicenseTwo { .. } => tr("title.license-two"),
+            Step::Status { .. } => tr("title.status"),
+            Step::LocateGoXLR { .. } => tr("title.locate"),
+            Step::UpdateMethod { .. } => tr("title.update-method"),
+            Step::SelectFile {
+                fetch_method: Some(method),
+                file,
+                download_error,
+                confirm_download,
+                cached,
+                ..
+            } => match method {
                 SelectUpdateOption::Download => match file {
-                    None => "Downloading Firmware",
-                    Some(_) => "Download Complete",
+                    None if download_error.is_some() => tr("title.download-failed"),
+                    Some(_) if cached.is_some() => tr("title.server-unavailable"),
+                    None if *confirm_download => tr("title.update-check-complete"),
+                    None => tr("title.downloading"),
+                    Some(_) => tr("title.download-complete"),
                 },
-                SelectUpdateOption::File => "Select Firmware File",
+                SelectUpdateOption::File => tr("title.select-file"),
             },
-            Step::SelectFile { .. } => "Select Firmware File",
-            Step::RunUpdate { .. } => "Updating..",
-            Step::Finish => "Finished.",
+            Step::SelectFile { .. } => tr("title.select-file"),
+            Step::Confirm { .. } => tr("title.confirm"),
+            Step::RunUpdate { .. } => tr("title.run-update"),
+            Step::Finish { .. } => tr("title.finish"),
         }
     }
 
     fn description(&self) -> &str {
         match self {
-            Step::Welcome => "Welcome to the GoXLR Firmware Updater",
-            Step::LicenseOne { .. } => {
-                "Please review the license terms before updating your firmware"
-            }
-            Step::LicenseTwo { .. } => {
-                "Please review the license terms before updating your firmware"
-            }
-            Step::Status { .. } => "Please ensure all GoXLR apps are closed before continuing",
-            Step::LocateGoXLR { .. } => "Please select a GoXLR from the list below",
-            Step::UpdateMethod { .. } => "Please Select the update method",
-            Step::SelectFile { fetch_method: Some(method), file, .. } => match method {
+            Step::Welcome { .. } => tr("description.welcome"),
+            Step::LicenseOne { .. } => tr("description.license"),
+            Step::LicenseTwo { .. } => tr("description.license"),
+            Step::Status { .. } => tr("description.status"),
+            Step::LocateGoXLR { .. } => tr("description.locate"),
+            Step::UpdateMethod { .. } => tr("description.update-method"),
+            Step::SelectFile {
+                fetch_method: Some(method),
+                file,
+                download_error,
+                confirm_download,
+                ..
+            } => match method {
                 SelectUpdateOption::Download => match file {
-                    None => "Please wait while the firmware downloads from TC-Helicon's servers",
-                    Some(_) => "Please continue when ready"
-                }
-                SelectUpdateOption::File => "Please select the correct firmware file for your GoXLR"
+                    None if download_error.is_some() => tr("description.download-failed"),
+                    None if *confirm_download => tr("description.confirm-download"),
+                    None => tr("description.downloading"),
+                    Some(_) => tr("description.download-complete"),
+                },
+                SelectUpdateOption::File => tr("description.select-file"),
+            },
+            Step::SelectFile { .. } => tr("description.select-file"),
+            Step::Confirm { .. } => tr("description.confirm"),
+            Step::RunUpdate { .. } => tr("description.run-update"),
+            Step::Finish { .. } => tr("description.finish"),
+        }
+    }
+
+    fn can_go_back(&self) -> bool {
+        match self {
+            // Don't let a download finish behind the user's back, and once the update has
+            // started, there's no going back.
+            Step::SelectFile {
+                fetch_method: Some(SelectUpdateOption::Download),
+                progress,
+                download_error,
+                download_cancel,
+                ..
+            } => {
+                *progress == 0
+                    || *progress == 100
+                    || download_error.is_some()
+                    || download_cancel.load(std::sync::atomic::Ordering::Relaxed)
             }
-            Step::SelectFile { .. } => "Please select the correct firmware file for your GoXLR",
-            Step::RunUpdate { .. } => "Firmware updating, do not power off your GoXLR or computer",
-            Step::Finish => "Update has been completed",
+            Step::RunUpdate { .. } | Step::Finish { .. } => false,
+            _ => true,
+        }
+    }
+
+    // Tidies up anything pre_display started, so the step can be shown again from scratch.
+    fn leave(&mut self) {
+        if let Step::LocateGoXLR { scan_cancel, .. } = self {
+            scan_cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
+
+        if let Step::SelectFile {
+            fetch_method,
+            progress,
+            rate,
+            source,
+            download_error,
+            latest,
+            confirm_download,
+            file,
+            details,
+            file_valid,
+            download_cancel,
+            cached,
+            ..
+        } = self
+        {
+            download_cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+            *progress = 0;
+            *rate = None;
+            *source = None;
+            *download_error = None;
+            *cached = None;
+            *latest = None;
+            *confirm_download = false;
+
+            // A file the user picked is kept, but a download will be fetched again.
+            if fetch_method == &Some(SelectUpdateOption::Download) {
+                *file = None;
+                *details = None;
+                *file_valid = false;
+            }
+            set_taskbar_progress(TaskbarProgress::None);
         }
     }
 
     fn can_continue(&self) -> bool {
         match self {
-            Step::Welcome => true,
-            Step::LicenseOne { agreed } => *agreed,
-            Step::LicenseTwo { agreed } => *agreed,
+            Step::Welcome { .. } => true,
+            Step::LicenseOne { agreed, .. } => *agreed,
+            Step::LicenseTwo { agreed, .. } => *agreed,
             Step::Status {
-                app, beta, util, ..
-            } => *app && *beta && *util,
+                app,
+                beta,
+                util,
+                fast_startup,
+                fast_startup_dismissed,
+                ..
+            } => *app && *beta && *util && (!*fast_startup || *fast_startup_dismissed),
             Step::LocateGoXLR { selected, .. } => selected.is_some(),
-            Step::UpdateMethod { .. } => true,
+            Step::UpdateMethod { download_url, .. } => {
+                download_url.trim().is_empty() || check_download_url(download_url).is_ok()
+            }
             Step::SelectFile { file_valid, .. } => *file_valid,
-            Step::RunUpdate { complete, .. } => *complete,
-            Step::Finish => false,
+            Step::Confirm {
+                device, firmware, ..
+            } => device.is_some() && firmware.is_some(),
+            Step::RunUpdate {
+                complete,
+                write_incomplete,
+                disconnected,
+                ..
+            } => *complete && !*write_incomplete && !*disconnected,
+            Step::Finish { .. } => false,
+        }
+    }
+
+    // Everything before the update is just gathering details, this is the point of no return.
+    fn next_label(&self) -> &str {
+        match self {
+            Step::Confirm { options, .. } if options.dry_run => tr("button.start-dry-run"),
+            Step::Confirm { .. } => tr("button.start-update"),
+            _ => tr("button.next"),
         }
     }
 
     fn view(&self) -> Element<PageMessages> {
         match self {
-            Step::Welcome => self.welcome(),
-            Step::LicenseOne { agreed } => self.license(*agreed, true),
-            Step::LicenseTwo { agreed } => self.license(*agreed, false),
+            Step::Welcome { newer_release } => self.welcome(newer_release),
+            Step::LicenseOne { agreed, read } => self.license(*agreed, *read, true),
+            Step::LicenseTwo { agreed, read } => self.license(*agreed, *read, false),
             Step::Status {
                 app,
                 beta,
                 util,
+                fast_startup,
+                fast_startup_dismissed,
+                confirm_close,
                 sender,
-            } => self.status(*app, *beta, *util, sender.clone()),
+            } => self.status(
+                *app,
+                *beta,
+                *util,
+                *fast_startup && !*fast_startup_dismissed,
+                *confirm_close,
+                sender.clone(),
+            ),
             Step::LocateGoXLR {
                 devices,
                 selected,
-                sender,
+                sender,
+                test_result,
+                update_all,
+                ignored,
+                show_ignored,
+                problems,
+                scan_cancel,
+                busy,
+                ..
+            } => self.find_goxlr(
+                *selected,
+                devices,
+                test_result,
+                *update_all,
+                ignored,
+                *show_ignored,
+                problems,
+                scan_cancel.load(std::sync::atomic::Ordering::Relaxed),
+                busy.load(std::sync::atomic::Ordering::Relaxed),
+                sender.clone(),
+            ),
+            Step::UpdateMethod {
+                storage: Some(storage),
+                storage_error,
+                ..
+            } => self.storage(storage, storage_error),
+            Step::UpdateMethod {
+                selected,
+                check_first,
+                download_url,
+                options,
                 ..
-            } => self.find_goxlr(*selected, devices, sender.clone()),
-            Step::UpdateMethod { selected, .. } => self.select_choice(*selected),
+            } => self.select_choice(*selected, *check_first, download_url, options),
             Step::SelectFile {
                 sender,
                 fetch_method,
                 details,
                 device,
+                latest,
+                confirm_download,
+                folder_matches,
                 progress,
+                rate,
+                source,
+                download_error,
+                cached,
                 file,
-                downgrade,
+                file_error,
+                accept_change,
+                override_blocked,
+                app_version,
+                app_acknowledged,
+                expected_hash,
+                dry_run,
                 ..
             } => self.select_file(
                 sender.clone(),
                 fetch_method,
                 details,
                 device,
+                latest,
+                *confirm_download,
+                folder_matches,
                 file,
+                file_error,
                 *progress,
-                downgrade,
+                *rate,
+                source,
+                download_error,
+                cached,
+                accept_change,
+                *override_blocked,
+                app_version,
+                *app_acknowledged,
+                expected_hash,
+                *dry_run,
             ),
+            Step::Confirm {
+                device,
+                firmware,
+                batch,
+                options,
+            } => self.confirm(device, firmware, batch, options),
             Step::RunUpdate {
+                device,
+                firmware,
+                batch,
+                identity,
+                completed,
                 stage,
+                retry,
                 percentage,
+                rate,
+                overall,
                 message,
+                awaiting_reboot,
                 complete,
                 is_error,
+                write_incomplete,
+                disconnected,
+                options,
+                ..
+            } => self.run_update(
+                &change_description(device, firmware),
+                batch,
+                identity,
+                completed,
+                stage,
+                retry,
+                *percentage,
+                *rate,
+                *overall,
+                message.clone(),
+                *awaiting_reboot,
+                *complete,
+                *is_error,
+                *write_incomplete,
+                *disconnected,
+                options,
+            ),
+            Step::Finish {
+                device,
+                firmware,
+                options,
+                succeeded,
+                elapsed,
+                version_records,
+                app_version,
+                reboot_check,
                 ..
-            } => self.run_update(stage, *percentage, message.clone(), *complete, *is_error),
-            Step::Finish => self.welcome(),
+            } => self.finish(
+                device,
+                firmware,
+                options,
+                *succeeded,
+                *elapsed,
+                version_records,
+                app_version,
+                reboot_check,
+            ),
         }
     }
 
@@ -701,46 +2315,94 @@ impl<'a> Step {
         self.description()
     }
 
-    fn welcome(&self) -> Element<'a, PageMessages> {
-        let message = r#"
-Welcome to the GoXLR Firmware Update Wizard, this tool will guide you through updating (or downgrading) your GoXLRs firmware.
-
-It's recommended that you close other programs, and make sure that both the official application and the utility are closed before proceeding.
-
-Click Next to continue.
-        "#;
-        container(message).into()
+    fn welcome(&self, newer_release: &Option<String>) -> Element<'a, PageMessages> {
+        let language = row![
+            text(tr("welcome.language")),
+            pick_list(
+                languages(),
+                Some(current_language()),
+                PageMessages::SelectLanguage
+            )
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let message = text(tr("welcome.message"));
+        let mut page = column![language, message].spacing(20);
+
+        if let Some(tag) = newer_release {
+            let available = tr_fill("welcome.newer-release", &[tag]);
+            let banner = column![
+                text(available),
+                text(release_page(tag)).size(12),
+                button(tr("welcome.open-release")).on_press(PageMessages::OpenReleasePage)
+            ]
+            .spacing(5);
+            page = page.push(banner);
+        }
+        page.into()
     }
 
-    fn license(&self, checked: bool, is_license_one: bool) -> Element<'a, PageMessages> {
+    fn license(
+        &self,
+        checked: bool,
+        read: bool,
+        is_license_one: bool,
+    ) -> Element<'a, PageMessages> {
         let message = if is_license_one {
             PageMessages::ToggleAcceptLicenseOne
         } else {
             PageMessages::ToggleAcceptLicenseTwo
         };
 
-        let license_text = if is_license_one {
-            LICENSE
+        let (license_text, license_url) = if is_license_one {
+            (LICENSE, LICENSE_URL)
+        } else {
+            (LICENSE_3RD_PARTY, LICENSE_3RD_PARTY_URL)
+        };
+
+        // If the text is missing, there's nothing to scroll through, so point the user at a copy
+        // they can read instead.
+        let available = !license_text.trim().is_empty();
+        let license: Element<_> = if available {
+            // Depending on the window size, the whole text may fit, in which case it's all been
+            // seen without any scrolling.
+            let license = scrollable(text(license_text).size(14))
+                .height(Length::Fill)
+                .on_scroll(|viewport| PageMessages::LicenseScrolled(viewport.relative_offset().y));
+            let on_fit = (!read).then_some(PageMessages::LicenseScrolled(1.));
+            FitCheck::new(license, on_fit).into()
         } else {
-            LICENSE_3RD_PARTY
+            let unavailable = format!("License text unavailable, see {}", license_url);
+            container(text(unavailable).size(14))
+                .height(Length::Fill)
+                .into()
         };
 
-        let license = scrollable(text(license_text).size(14)).height(Length::Fill);
+        // The checkbox does nothing until the user has at least scrolled to the end.
+        let can_accept = read || !available;
+        let label = if can_accept {
+            "I accept the terms of the License Agreement"
+        } else {
+            "Please scroll to the end of the License Agreement"
+        };
+        let message = if can_accept {
+            message
+        } else {
+            PageMessages::NoneBool
+        };
 
         // We don't have a monospace font yet, so we'll have to do this the old fashioned way.
-        let check = container(checkbox(
-            "I accept the terms of the License Agreement",
-            checked,
-            message,
-        ))
-        .padding(Padding {
+        let check = container(checkbox(label, checked, message)).padding(Padding {
             top: 10.0,
             right: 0.0,
             bottom: 0.0,
             left: 0.0,
         });
 
-        container(column![license, check]).into()
+        container(column![license, check])
+            .height(Length::Fill)
+            .into()
     }
 
     fn status(
@@ -748,6 +2410,8 @@ Click Next to continue.
         app: bool,
         beta: bool,
         util: bool,
+        fast_startup: bool,
+        confirm_close: bool,
         sender: UnboundedSender<Message>,
     ) -> Element<'a, PageMessages> {
         // Ok, despite there being 3 bools here, due to the way the apps are designed, only one
@@ -774,53 +2438,157 @@ Click Next to continue.
             left: 0.0,
         });
 
+        // Not everyone knows how to close the apps (especially the Utility), so offer to do it.
+        if !(app && beta && util) {
+            let close = if confirm_close {
+                let warning = "This will force the GoXLR apps to close, any changes which \
+                    haven't been saved to your profiles will be lost.";
+                let buttons = row![
+                    button("Close Apps").on_press(PageMessages::CloseApps),
+                    button("Cancel").on_press(PageMessages::ConfirmCloseApps(false)),
+                ]
+                .spacing(10);
+                column![text(warning), buttons].spacing(10)
+            } else {
+                let button =
+                    button("Close Automatically").on_press(PageMessages::ConfirmCloseApps(true));
+                column![button]
+            };
+            return container(column![msg, close]).into();
+        }
+
+        if fast_startup {
+            let advice = "Windows Fast Startup is enabled. Shutting down with it enabled doesn't \
+                fully reset USB devices, which can cause the update to fail. If this is the first \
+                time using your GoXLR since turning your computer on, we'd recommend using \
+                'Restart' (which always performs a clean boot) before updating.";
+            let dismiss = checkbox(
+                "Continue without restarting",
+                false,
+                PageMessages::DismissFastStartup,
+            );
+            return container(column![msg, text(advice), dismiss].spacing(10)).into();
+        }
+
         container(column![msg]).into()
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn find_goxlr(
         &self,
         selected: Option<usize>,
         list: &Option<Vec<Device>>,
+        test_result: &Option<String>,
+        update_all: bool,
+        ignored: &[String],
+        show_ignored: bool,
+        problems: &[String],
+        cancelled: bool,
+        busy: bool,
         sender: UnboundedSender<Message>,
     ) -> Element<'a, PageMessages> {
         if let Some(list) = list {
             if list.is_empty() {
-                println!("No Devices..");
-                return container(column![text(
-                    "No GoXLRs Found, please attach one and restart."
-                )])
-                .into();
-            } else if list.len() == 1 {
-                println!("One Device..");
-                // Only one device, select and skip to the next page.
+                debug!("No Devices..");
+                let message = if cancelled {
+                    "Scan stopped before any GoXLRs were found."
+                } else {
+                    "No GoXLRs Found, please attach one and rescan."
+                };
+                let mut rescan = button("Rescan");
+                if !busy {
+                    rescan = rescan.on_press(PageMessages::RescanDevices);
+                }
+                let mut page = column![text(message), rescan].spacing(10);
+                if busy {
+                    page = page.push(text(BUSY_MESSAGE));
+                }
+                if let Some(note) = problem_devices(problems) {
+                    page = page.push(note);
+                }
+                if let Some(note) = ignored_devices(ignored, show_ignored) {
+                    page = page.push(note);
+                }
+                return container(page).into();
+            }
+
+            // Only one device, so select it, we still stay on this page in case the user wants to
+            // test it before continuing.
+            if list.len() == 1 && selected.is_none() {
                 let _ = sender.send(Message::PageMessage(PageMessages::SelectDevice(0)));
-                let _ = sender.send(Message::NextPressed);
-            } else {
-                return container(
-                    column(
-                        list.iter()
-                            .cloned()
-                            .enumerate()
-                            .map(|(i, device)| {
-                                let label = format!(
-                                    "[{}] GoXLR {:?}",
-                                    device.device_serial, device.device_type
-                                );
-                                radio(label, i, selected, PageMessages::SelectDevice)
-                            })
-                            .map(Element::from)
-                            .collect(),
-                    )
-                    .spacing(10),
-                )
-                .into();
-            }
-        }
-
-        container(column![text("Please Wait..")]).into()
+            }
+
+            let devices = column(
+                list.iter()
+                    .cloned()
+                    .enumerate()
+                    .map(|(i, device)| {
+                        let label =
+                            format!("[{}] GoXLR {:?}", device.device_serial, device.device_type);
+                        radio(label, i, selected, PageMessages::SelectDevice)
+                    })
+                    .map(Element::from)
+                    .collect(),
+            )
+            .spacing(10);
+
+            // Nothing gets sent to the devices while one of them is being updated.
+            let mut test = button("Test Communication");
+            let mut rescan = button("Rescan");
+            if !busy {
+                if selected.is_some() {
+                    test = test.on_press(PageMessages::TestDevice);
+                }
+                rescan = rescan.on_press(PageMessages::RescanDevices);
+            }
+            let actions = row![test, horizontal_space(Length::Fill), rescan].spacing(10);
+
+            let mut page =
+                column![devices, Space::new(Length::Fill, Length::Fill), actions].spacing(10);
+            if let Some(result) = test_result {
+                page = page.push(text(result));
+            } else if busy {
+                page = page.push(text(BUSY_MESSAGE));
+            }
+            if cancelled {
+                page = page.push(text("Scan stopped early, some GoXLRs may be missing."));
+            }
+            if let Some(note) = problem_devices(problems) {
+                page = page.push(note);
+            }
+            if let Some(note) = ignored_devices(ignored, show_ignored) {
+                page = page.push(note);
+            }
+
+            // All the devices get the same firmware, so any which it isn't for will be skipped.
+            if list.len() > 1 {
+                let label = format!("Update all {} GoXLRs with the same firmware", list.len());
+                page = page.push(checkbox(label, update_all, PageMessages::SetUpdateAll));
+
+                let same_type = list.iter().all(|d| d.device_type == list[0].device_type);
+                if update_all && !same_type {
+                    let note = "GoXLRs which don't match the selected firmware will be skipped.";
+                    page = page.push(text(note).size(14));
+                }
+            }
+            return container(page).into();
+        }
+
+        let cancel = if cancelled {
+            button("Stopping..")
+        } else {
+            button("Stop Scanning").on_press(PageMessages::CancelDeviceScan)
+        };
+        container(column![text("Please Wait.."), cancel].spacing(10)).into()
     }
 
-    fn select_choice(&self, selected: Option<SelectUpdateOption>) -> Element<'a, PageMessages> {
+    fn select_choice(
+        &self,
+        selected: Option<SelectUpdateOption>,
+        check_first: bool,
+        download_url: &str,
+        options: &UpdateOptions,
+    ) -> Element<'a, PageMessages> {
         let download = radio(
             "Download Latest (EXPERIMENTAL)",
             SelectUpdateOption::Download,
@@ -834,7 +2602,125 @@ Click Next to continue.
             PageMessages::SelectFirmwareOption,
         );
 
-        container(column![download, file].spacing(10)).into()
+        let check = checkbox(
+            "Check the latest version before downloading",
+            check_first,
+            PageMessages::SetCheckBeforeDownload,
+        );
+        let folder = row![
+            text(format!("Save to: {}", download_dir().to_string_lossy())).size(12),
+            horizontal_space(Length::Fill),
+            button(text("Change").size(12)).on_press(PageMessages::SelectDownloadFolder),
+        ];
+        let check = container(column![check, folder].spacing(5)).padding(Padding {
+            top: 0.0,
+            right: 0.0,
+            bottom: 0.0,
+            left: 30.0,
+        });
+
+        let choices = column![download, check, file].spacing(10);
+
+        // Options for people who know what they're doing..
+        let pause = checkbox(
+            "Wait for confirmation before rebooting the GoXLR",
+            options.pause_before_reboot,
+            PageMessages::SetPauseBeforeReboot,
+        );
+        let recovery = checkbox(
+            "Recovery: leave the GoXLR in update mode if the update fails",
+            options.stay_in_update_mode_on_failure,
+            PageMessages::SetStayInUpdateMode,
+        );
+        let reboot_check = checkbox(
+            "Check the installed firmware once the GoXLR has rebooted",
+            options.check_after_reboot,
+            PageMessages::SetCheckAfterReboot,
+        );
+        let mut url = column![text_input(
+            "Download from URL instead of the latest (Optional)",
+            download_url
+        )
+        .on_input(PageMessages::SetDownloadUrl)
+        .size(12)]
+        .spacing(2);
+        if !download_url.trim().is_empty() {
+            if let Err(error) = check_download_url(download_url) {
+                url = url.push(text(error).size(12));
+            }
+        }
+        let storage = button(text("Manage Downloaded Firmware").size(14))
+            .on_press(PageMessages::ToggleStorage);
+        let advanced = column![
+            text("Advanced").size(14),
+            pause,
+            recovery,
+            reboot_check,
+            url,
+            storage
+        ]
+        .spacing(10);
+
+        container(column![
+            choices,
+            Space::new(Length::Fill, Length::Fill),
+            advanced
+        ])
+        .into()
+    }
+
+    fn storage(
+        &self,
+        storage: &[(PathBuf, FirmwareInfo, u64)],
+        error: &Option<String>,
+    ) -> Element<'a, PageMessages> {
+        let mut page = column![].spacing(10);
+
+        if storage.is_empty() {
+            page = page.push(text("No downloaded firmware found."));
+        } else {
+            let files = column(
+                storage
+                    .iter()
+                    .map(|(path, info, size)| {
+                        let name = path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        let label = format!(
+                            "{} - GoXLR {:?} {} ({})",
+                            name,
+                            info.device,
+                            version_label(info.device, info.version),
+                            display_size(*size)
+                        );
+                        text(label).size(14)
+                    })
+                    .map(Element::from)
+                    .collect(),
+            );
+            page = page.push(scrollable(files).height(150));
+        }
+
+        if let Some(error) = error {
+            page = page.push(text(error));
+        }
+
+        let mut clear = button("Clear Downloads");
+        if !storage.is_empty() {
+            clear = clear.on_press(PageMessages::ClearStorage);
+        }
+        let buttons = row![
+            button("Open Folder").on_press(PageMessages::OpenStorageFolder),
+            clear,
+            horizontal_space(Length::Fill),
+            button("Done").on_press(PageMessages::ToggleStorage),
+        ]
+        .spacing(10);
+        page = page.push(Space::new(Length::Fill, Length::Fill));
+        page = page.push(buttons);
+
+        container(page).into()
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -844,14 +2730,83 @@ Click Next to continue.
         fetch_method: &Option<SelectUpdateOption>,
         details: &Option<FirmwareDetails>,
         device: &Option<Device>,
+        latest: &Option<FirmwareInfo>,
+        confirm_download: bool,
+        folder_matches: &Option<Vec<(PathBuf, FirmwareInfo)>>,
         file: &Option<PathBuf>,
+        file_error: &Option<String>,
         progress: u8,
-        downgrade: &bool,
+        rate: Option<TransferStats>,
+        source: &Option<String>,
+        download_error: &Option<String>,
+        cached: &Option<FirmwareInfo>,
+        accept_change: &bool,
+        override_blocked: bool,
+        app_version: &Option<String>,
+        app_acknowledged: bool,
+        expected_hash: &str,
+        dry_run: bool,
     ) -> Element<'a, PageMessages> {
         // For the selection, there are now two options.. The first is waiting for a download to
         // complete and providing a file, the second is allowing the user to directly select a
         // file, so we need a bit of potential sh
 
+        // If the download fell over, there's nothing to show here beyond the error and a way out.
+        if let Some(error) = download_error {
+            let _ = sender.send(Message::PageMessage(PageMessages::SetFirmwareValid(false)));
+
+            let buttons = row![
+                button("Retry Download").on_press(PageMessages::RetryDownload),
+                button("Select a File Instead").on_press(PageMessages::UseFileInstead),
+            ]
+            .spacing(10);
+            let folder = button(text("Change Download Folder").size(14))
+                .on_press(PageMessages::SelectDownloadFolder);
+
+            return container(
+                column![
+                    text("Unable to download the firmware:"),
+                    text(error),
+                    Space::new(Length::Fill, 20),
+                    buttons,
+                    folder
+                ]
+                .spacing(5),
+            )
+            .into();
+        }
+
+        // We've checked what's on the server, let the user decide whether to grab it.
+        if let (true, Some(latest)) = (confirm_download, latest) {
+            let _ = sender.send(Message::PageMessage(PageMessages::SetFirmwareValid(false)));
+
+            let latest_version = version_label(latest.device, latest.version);
+            let mut messages = column![text(format!("Latest Firmware: {}", latest_version))];
+            if let Some(device) = device {
+                let current = version_label(device.device_type, device.version);
+                messages = messages.push(text(format!("Current Firmware: {}", current)));
+
+                let verdict = if latest.version.cmp_to(&device.version) == VersionChange::Upgrade {
+                    "A newer firmware is available for your GoXLR."
+                } else {
+                    "Your GoXLR is already running the latest firmware."
+                };
+                messages = messages.push(Space::new(Length::Fill, 10));
+                messages = messages.push(text(verdict));
+            }
+            messages = messages.push(Space::new(Length::Fill, 20));
+            messages =
+                messages.push(button("Download Firmware").on_press(PageMessages::StartDownload));
+
+            return container(messages.spacing(5)).into();
+        }
+
+        let folder_button = if fetch_method == &Some(SelectUpdateOption::File) {
+            Some(button("Select Folder").on_press(PageMessages::SelectFirmwareFolder))
+        } else {
+            None
+        };
+
         let button = match fetch_method {
             None => Some(button("Select Firmware")),
             Some(option) => match option {
@@ -865,9 +2820,9 @@ Click Next to continue.
         // Firstly, create a top row for selecting the file..
         let mut header = row![];
         let file_text = if let Some(file) = file {
-            format!("{}", file.file_name().unwrap().to_string_lossy())
+            display_file_name(file)
         } else {
-            "No File Selected".to_string()
+            "No File Selected (or drop one here)".to_string()
         };
 
         // We need to define the 'File' box based on whether we're downloading a firmware, or
@@ -885,7 +2840,13 @@ Click Next to continue.
                     });
 
             if let Some(file) = file {
-                let file_text = format!("{}", file.file_name().unwrap().to_string_lossy());
+                let file_text = match cached {
+                    Some(info) => format!(
+                        "Using previously downloaded firmware {}",
+                        version_label(info.device, info.version)
+                    ),
+                    None => display_file_name(file),
+                };
                 container(text(file_text))
                     .padding(Padding {
                         top: 5.0,
@@ -895,7 +2856,15 @@ Click Next to continue.
                     })
                     .width(Length::Fill)
             } else {
-                container(row![progress_bar, progress_text])
+                let cancel = Button::new("Cancel").on_press(PageMessages::CancelDownload);
+                let mut download = column![row![progress_bar, progress_text, cancel]];
+                if let Some(rate) = rate {
+                    download = download.push(text(rate.to_string()).size(14));
+                }
+                if let Some(source) = source {
+                    download = download.push(text(format!("From: {}", source)).size(12));
+                }
+                container(download)
             }
         } else {
             container(text(file_text))
@@ -912,19 +2881,66 @@ Click Next to continue.
         if let Some(button) = button {
             header = header.push(button);
         }
-        let header = container(header).padding(Padding {
+        if let Some(folder_button) = folder_button {
+            header = header.push(folder_button);
+        }
+        let header = container(header.spacing(5)).padding(Padding {
             top: 0.0,
             right: 0.0,
             bottom: 10.0,
             left: 0.0,
         });
 
+        // If a folder has been scanned, list everything in there that'll work with this device.
+        let folder_list = folder_matches.as_ref().map(|matches| {
+            let list: Element<_> = if matches.is_empty() {
+                text("No compatible firmware found in the selected folder").into()
+            } else {
+                let selected = matches
+                    .iter()
+                    .position(|(path, _)| Some(path) == file.as_ref());
+                let options = matches.iter().enumerate().map(|(i, (path, info))| {
+                    let version = version_label(info.device, info.version);
+                    let label = format!("{} ({})", version, display_file_name(path));
+                    Element::from(radio(
+                        label,
+                        i,
+                        selected,
+                        PageMessages::SelectFolderFirmware,
+                    ))
+                });
+                scrollable(column(options.collect()).spacing(5))
+                    .height(70)
+                    .into()
+            };
+            container(list).padding(Padding {
+                top: 0.0,
+                right: 0.0,
+                bottom: 10.0,
+                left: 0.0,
+            })
+        });
+
         let mut messages = column![];
         let mut valid = true;
-        let mut is_downgrade = false;
-        let mut is_reinstall = false;
+        let mut change = VersionChange::Upgrade;
+        let mut blocked = None;
 
         if let Some(details) = details {
+            // Similarly named files in different folders are easy to mix up, so show where this
+            // one actually came from.
+            let location = format!(
+                "{} ({})",
+                display_path(&details.path, 70),
+                display_size(details.size)
+            );
+            let full_path = details.path.to_string_lossy().to_string();
+            messages = messages.push(tooltip(
+                text(location).size(12),
+                full_path,
+                tooltip::Position::Bottom,
+            ));
+
             // We need the current device info here..
             if let Some(device) = device {
                 if device.device_type != details.device_type {
@@ -939,45 +2955,149 @@ Click Next to continue.
                         expected
                     )));
                     valid = false;
-                } else if version_newer_or_equal_to(&device.version, details.version) {
-                    is_downgrade = true;
+                } else {
+                    change = details.version.cmp_to(&device.version);
                 }
 
                 if valid {
-                    let current = text(format!("Current Firmware: {}", device.version));
-                    let new_version = text(format!("Selected Firmware: {}", details.version));
+                    let current = version_label(device.device_type, device.version);
+                    let selected = version_label(details.device_type, details.version);
+                    let current = text(format!("Current Firmware: {}", current));
+                    let new_version = text(format!("Selected Firmware: {}", selected));
                     messages = messages.push(new_version);
                     messages = messages.push(current);
+
+                    // Always spell out what's about to happen, not just when it's risky.
+                    let verdict = match change {
+                        VersionChange::Reinstall => {
+                            format!("This will reinstall firmware {}.", device.version)
+                        }
+                        VersionChange::Downgrade => format!(
+                            "This is a downgrade from {} to {}.",
+                            device.version, details.version
+                        ),
+                        VersionChange::Upgrade => format!(
+                            "This is an upgrade from {} to {}.",
+                            device.version, details.version
+                        ),
+                    };
+                    messages = messages.push(Space::new(Length::Fill, 5));
+                    messages = messages.push(text(verdict));
                 }
-                if is_downgrade && (device.version == details.version) {
-                    is_reinstall = true;
+            }
+
+            if valid {
+                match check_policy(details.device_type, details.version) {
+                    FirmwarePolicy::Allowed => {}
+                    FirmwarePolicy::Blocked(reason) => blocked = Some(reason),
+                    FirmwarePolicy::NotApproved => {
+                        messages = messages.push(text(
+                            "This firmware version has not been approved for use on this system",
+                        ));
+                        valid = false;
+                    }
                 }
             }
         } else if file.is_some() {
-            messages = messages.push("Selected file is not a GoXLR Firmware");
+            // The check says why the file was turned down, which is more use than a guess.
+            let message = match file_error {
+                Some(error) => error.clone(),
+                None => String::from("Selected file is not a GoXLR Firmware"),
+            };
+            messages = messages.push(text(message));
             valid = false;
         } else {
             valid = false;
         }
 
-        if valid && is_downgrade {
-            let task = if is_reinstall {
-                "Reinstall"
-            } else {
-                "Downgrade"
-            };
+        // For anyone who wants to be sure they've got exactly the file they were expecting.
+        if let (true, Some(details)) = (valid, details) {
+            messages = messages.push(Space::new(Length::Fill, 5));
+            messages = messages.push(text(format!("SHA-256: {}", details.sha256)).size(12));
+            messages = messages.push(
+                text_input("Expected SHA-256 (Optional)", expected_hash)
+                    .on_input(PageMessages::SetExpectedHash)
+                    .size(12),
+            );
+
+            let expected = expected_hash.trim();
+            if !expected.is_empty() && !expected.eq_ignore_ascii_case(&details.sha256) {
+                let message = "The firmware does not match the expected SHA-256";
+                messages = messages.push(text(message).size(12));
+                valid = false;
+            }
+        }
+
+        if valid && change != VersionChange::Upgrade {
+            messages = messages.push(Space::new(Length::Fill, Length::Fill));
+
+            // There's no 'repair' mode on the device, a reinstall goes through exactly the same
+            // erase and write cycle as any other update, so make sure that's understood.
+            if change == VersionChange::Reinstall {
+                messages = messages.push(text(
+                    "This is not a quick repair, the firmware will be fully erased and rewritten.",
+                ));
+            }
+
+            messages = messages.push(checkbox(
+                format!("Confirm Firmware {}", change.label()),
+                *accept_change,
+                PageMessages::SetAcceptVersionChange,
+            ));
+        }
+
+        if let (true, Some(reason)) = (valid, &blocked) {
+            messages = messages.push(Space::new(Length::Fill, Length::Fill));
+            messages = messages.push(text(format!(
+                "This firmware version is known to have issues ({}) and flashing it is not \
+                recommended.",
+                reason
+            )));
+            messages = messages.push(checkbox(
+                "I understand the risk, and want to flash it anyway",
+                override_blocked,
+                PageMessages::SetOverrideBlocked,
+            ));
+        }
+
+        // Not a reason to stop, but worth knowing before the App stops working with the device.
+        let app_needed = match (valid, details, app_version) {
+            (true, Some(details), Some(installed)) => {
+                required_app_version(details.device_type, details.version, installed)
+                    .map(|required| (installed, required))
+            }
+            _ => None,
+        };
+        if let Some((installed, required)) = &app_needed {
+            messages = messages.push(Space::new(Length::Fill, Length::Fill));
+            messages = messages.push(text(format!(
+                "This firmware needs GoXLR App version {} or newer, but you have {}. Update \
+                your App after flashing, or the device may not work correctly.",
+                required, installed
+            )));
+            messages = messages.push(checkbox(
+                "I understand, and will update the App",
+                app_acknowledged,
+                PageMessages::SetAcknowledgeAppVersion,
+            ));
+        }
 
+        // Lets the user make sure everything lines up, without committing to the update.
+        if valid {
             messages = messages.push(Space::new(Length::Fill, Length::Fill));
             messages = messages.push(checkbox(
-                format!("Confirm Firmware {}", task),
-                *downgrade,
-                PageMessages::SetAcceptDowngrade,
+                "Dry run (check compatibility only, don't flash)",
+                dry_run,
+                PageMessages::SetDryRun,
             ));
         }
 
         // We'll get called a few times for any changes, so can inform the parent if we're ready
         // to go.
-        let ready = !(!valid || is_downgrade && !*downgrade);
+        let ready = !(!valid
+            || change != VersionChange::Upgrade && !*accept_change
+            || blocked.is_some() && !override_blocked
+            || app_needed.is_some() && !app_acknowledged);
         let _ = sender.send(Message::PageMessage(PageMessages::SetFirmwareValid(ready)));
 
         // We need this so we can actually do the firmware update, if the user changes the file,
@@ -999,31 +3119,172 @@ Click Next to continue.
             left: 0.0,
         });
 
-        container(column![header, Rule::horizontal(5), message_container]).into()
+        let mut page = column![header];
+        if let Some(folder_list) = folder_list {
+            page = page.push(folder_list);
+        }
+        page = page.push(Rule::horizontal(5));
+        page = page.push(message_container);
+
+        container(page).into()
+    }
+
+    fn confirm(
+        &self,
+        device: &Option<Device>,
+        firmware: &Option<FirmwareDetails>,
+        batch: &[Device],
+        options: &UpdateOptions,
+    ) -> Element<'a, PageMessages> {
+        let (device, firmware) = match (device, firmware) {
+            (Some(device), Some(firmware)) => (device, firmware),
+            _ => return container(text("No GoXLR or firmware has been selected.")).into(),
+        };
+
+        let mut page = column![].spacing(5);
+        if batch.is_empty() {
+            page = page.push(text(format!(
+                "GoXLR {:?}: {}",
+                device.device_type, device.device_serial
+            )));
+        } else {
+            let serials = batch
+                .iter()
+                .map(|device| device.device_serial.clone())
+                .collect::<Vec<_>>()
+                .join(", ");
+            page = page.push(text(format!("{} GoXLRs: {}", batch.len(), serials)));
+        }
+
+        let current = version_label(device.device_type, device.version);
+        let selected = version_label(firmware.device_type, firmware.version);
+        page = page.push(text(format!("Current Firmware: {}", current)));
+        page = page.push(text(format!("New Firmware: {}", selected)));
+
+        let change = firmware.version.cmp_to(&device.version);
+        page = page.push(text(format!("Update Type: {}", change.label())));
+        page = page.push(text(change.describe(device.version, firmware.version)));
+        page = page.push(Space::new(Length::Fill, 10));
+
+        let warning = if options.dry_run {
+            "This is a dry run, the firmware will be checked against the GoXLR but nothing will \
+            be written to it."
+        } else {
+            "The GoXLR's firmware will be erased and rewritten. Once started, do not disconnect \
+            the GoXLR or turn off your computer until the update has finished."
+        };
+        page = page.push(text(warning));
+
+        let unstable = batch.iter().chain([device]).any(|d| d.connection_unstable);
+        if unstable && !options.dry_run {
+            page = page.push(Space::new(Length::Fill, 5));
+            page = page.push(text(
+                "Warning: This GoXLR disconnected while it was being detected, which usually \
+                means an unreliable USB hub, cable or port. A disconnect during the update can \
+                leave it unusable, please plug it directly into a USB port on the back of your \
+                computer, and go back to rescan before continuing.",
+            ));
+        }
+
+        container(page).into()
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn run_update(
         &self,
+        change: &Option<String>,
+        batch: &[(Device, BatchStatus)],
+        identity: &Option<(u64, String)>,
+        completed: &[String],
         stage: &String,
+        retry: &Option<String>,
         percent: u8,
+        rate: Option<TransferStats>,
+        overall: u8,
         message: Option<String>,
+        awaiting_reboot: bool,
         is_complete: bool,
         is_error: bool,
+        write_incomplete: bool,
+        disconnected: bool,
+        options: &UpdateOptions,
     ) -> Element<'a, PageMessages> {
-        let progress_bar = progress_bar(0.0..=100.0, percent as f32).width(Length::Fill);
-        let progress_text = container(text(format!("{}%", percent)))
-            .width(50)
-            .padding(Padding {
-                top: 5.0,
-                right: 0.0,
-                bottom: 0.0,
-                left: 5.0,
-            });
-        let row = row![progress_bar, progress_text];
-
         let mut page = column![];
+
+        // Devices in a batch can each be on a different version, so there's no one answer there.
+        if let (Some(change), true) = (change, batch.is_empty()) {
+            page = page.push(text(change));
+        }
+        if let Some((size, hash)) = identity {
+            page = page.push(text(tr_fill("update.firmware-size", &[size])).size(12));
+            page = page.push(text(tr_fill("update.firmware-hash", &[hash])).size(12));
+            page = page.push(Space::new(Length::Fill, 5));
+        }
+        page = page.push(text(tr("update.overall-progress")));
+        page = page.push(percent_bar(overall));
+        page = page.push(Space::new(Length::Fill, 10));
         page = page.push(text(stage));
-        page = page.push(row);
+        page = page.push(percent_bar(percent));
+        if let (Some(rate), false) = (rate, is_complete) {
+            page = page.push(text(rate.to_string()).size(14));
+        }
+        if let (Some(retry), false) = (retry, is_complete) {
+            page = page.push(text(retry).size(14));
+        }
+
+        if !completed.is_empty() {
+            let marker = match (is_complete, is_error) {
+                (true, false) => "✓",
+                (true, true) => "✗",
+                _ => "→",
+            };
+            let log = column(
+                completed
+                    .iter()
+                    .map(|stage| text(format!("✓ {}", stage)).size(14))
+                    .chain(std::iter::once(
+                        text(format!("{} {}", marker, stage)).size(14),
+                    ))
+                    .map(Element::from)
+                    .collect(),
+            );
+            page = page.push(Space::new(Length::Fill, 10));
+            page = page.push(scrollable(log).height(80));
+        }
+
+        if !batch.is_empty() {
+            let current = batch
+                .iter()
+                .position(|(_, status)| *status == BatchStatus::Updating);
+            if let Some(index) = current {
+                let (device, _) = &batch[index];
+                page = page.push(Space::new(Length::Fill, 5));
+                let position = [
+                    &(index + 1) as &dyn Display,
+                    &batch.len(),
+                    &device.device_serial,
+                ];
+                page = page.push(text(tr_fill("update.batch-progress", &position)));
+            }
+
+            let queue = column(
+                batch
+                    .iter()
+                    .map(|(device, status)| {
+                        let label = format!(
+                            "[{}] GoXLR {:?}: {}",
+                            device.device_serial,
+                            device.device_type,
+                            status.label()
+                        );
+                        text(label).size(14)
+                    })
+                    .map(Element::from)
+                    .collect(),
+            );
+            page = page.push(Space::new(Length::Fill, 10));
+            page = page.push(scrollable(queue).height(60));
+        }
 
         if let Some(message) = message {
             page = page.push(text(message));
@@ -1031,44 +3292,256 @@ Click Next to continue.
 
         page = page.push(Space::new(Length::Fill, 30));
 
+        if awaiting_reboot {
+            page = page.push(button(tr("update.reboot")).on_press(PageMessages::RebootDevice));
+        }
+
+        if is_complete && write_incomplete {
+            let message = if options.stay_in_update_mode_on_failure {
+                tr("update.recovery-mode")
+            } else {
+                tr("update.write-incomplete")
+            };
+            page = page.push(text(message));
+            if let Some(note) = log_note() {
+                page = page.push(note);
+            }
+            page = page.push(save_log_button());
+            page = page.push(button(tr("update.run-again")).on_press(PageMessages::RetryUpdate));
+            return container(page).into();
+        }
+
+        // The error message already says what to do, there's nothing we can do from here.
+        if is_complete && disconnected {
+            if let Some(note) = log_note() {
+                page = page.push(note);
+            }
+            page = page.push(save_log_button());
+            return container(page).into();
+        }
+
         if is_complete {
             if is_error {
-                let message = "An error occurred updating your GoXLR, it has been rebooted back into it's previous firmware.";
+                let message = tr("update.error");
                 page = page.push(text(message));
+                if let Some(note) = log_note() {
+                    page = page.push(note);
+                }
+                page = page.push(save_log_button());
             } else {
-                let message = "Your GoXLR was successfully updated and has been rebooted.";
+                let message = tr("update.success");
                 page = page.push(text(message));
             }
-            let message = "You can now close this tool, and restart the GoXLR App of your choice!";
+            let message = tr("update.close");
             page = page.push(message);
         }
 
         container(page).into()
     }
+
+    #[allow(clippy::too_many_arguments)]
+    fn finish(
+        &self,
+        device: &Option<Device>,
+        firmware: &Option<FirmwareDetails>,
+        options: &UpdateOptions,
+        succeeded: Option<bool>,
+        elapsed: Option<Duration>,
+        version_records: &[PathBuf],
+        app_version: &Option<String>,
+        reboot_check: &Option<Result<VersionNumber, String>>,
+    ) -> Element<'a, PageMessages> {
+        let mut page = column![].spacing(10);
+
+        if let (Some(true), Some(firmware)) = (succeeded, firmware) {
+            let version = version_label(firmware.device_type, firmware.version);
+            let message = format!("Your GoXLR is now running firmware {}.", version);
+            page = page.push(text(message));
+
+            let mut summary = column![].spacing(2);
+            if let Some(device) = device {
+                let previous = version_label(device.device_type, device.version);
+                summary = summary.push(text(format!("Device: {}", device.device_serial)).size(14));
+                let updated = match firmware.version.cmp_to(&device.version) {
+                    VersionChange::Reinstall => format!("Reinstalled {}", version),
+                    VersionChange::Downgrade => {
+                        format!("Downgraded from {} to {}", previous, version)
+                    }
+                    VersionChange::Upgrade => format!("Updated from {} to {}", previous, version),
+                };
+                summary = summary.push(text(updated).size(14));
+            }
+            if let Some(elapsed) = elapsed {
+                let secs = elapsed.as_secs();
+                let taken = format!("Time Taken: {}m {:02}s", secs / 60, secs % 60);
+                summary = summary.push(text(taken).size(14));
+            }
+            if version_records.is_empty() {
+                summary = summary
+                    .push(text("No record of the previous firmware version was saved").size(14));
+            }
+            for record in version_records {
+                let saved = format!(
+                    "Previous firmware version noted in {}",
+                    record.to_string_lossy()
+                );
+                summary = summary.push(text(saved).size(14));
+            }
+            page = page.push(summary);
+
+            if options.check_after_reboot && !options.dry_run {
+                let check = match reboot_check {
+                    None => {
+                        String::from("Waiting for the GoXLR to restart to check its firmware..")
+                    }
+                    Some(Ok(running)) if *running == firmware.version => {
+                        format!("Verified: device now running {}", running)
+                    }
+                    Some(Ok(running)) => format!(
+                        "Warning: The GoXLR is reporting firmware {}, not {}, please try the \
+                        update again.",
+                        running, firmware.version
+                    ),
+                    Some(Err(error)) => format!(
+                        "Warning: Unable to check the firmware after rebooting: {}",
+                        error
+                    ),
+                };
+                page = page.push(text(check));
+            }
+
+            // We don't know exactly which App releases go with which firmware, but if we can see
+            // an App installed, we can at least point the user in the right direction.
+            if let (Some(device), Some(app_version)) = (device, app_version) {
+                let advice = match device.version.cmp(&firmware.version) {
+                    Ordering::Less => Some(format!(
+                        "Your installed GoXLR App ({}) may need updating to work with this \
+                        firmware, if it has any problems please install the latest GoXLR App.",
+                        app_version
+                    )),
+                    Ordering::Greater => Some(format!(
+                        "Your installed GoXLR App ({}) may be too new for this firmware, if it \
+                        has any problems please install the App release made for firmware {}.",
+                        app_version, firmware.version
+                    )),
+                    Ordering::Equal => None,
+                };
+
+                if let Some(advice) = advice {
+                    page = page.push(text(advice));
+                }
+            }
+        } else {
+            let message = "The update did not complete, your GoXLR has been left on its \
+                previous firmware.";
+            page = page.push(text(message));
+        }
+
+        let message = "You can now close this tool, and restart the GoXLR App of your choice!";
+        page = page.push(text(message));
+
+        container(page).into()
+    }
+}
+
+// A collapsible list of devices that were found, but couldn't be used.
+// Unlike the ignored devices, these are GoXLRs, so always say why they can't be selected.
+fn problem_devices<'a>(problems: &[String]) -> Option<Element<'a, PageMessages>> {
+    if problems.is_empty() {
+        return None;
+    }
+
+    let found = if problems.len() == 1 {
+        String::from("1 device found but couldn't be read:")
+    } else {
+        format!("{} devices found but couldn't be read:", problems.len())
+    };
+    let mut note = column![text(found).size(14)].spacing(2);
+    for problem in problems {
+        note = note.push(text(format!("  {}", problem)).size(14));
+    }
+    Some(note.into())
 }
 
-pub fn version_newer_or_equal_to(version: &VersionNumber, comparison: VersionNumber) -> bool {
-    match version.0.cmp(&comparison.0) {
-        Ordering::Greater => return true,
-        Ordering::Less => return false,
-        Ordering::Equal => {}
+fn ignored_devices<'a>(ignored: &[String], expanded: bool) -> Option<Element<'a, PageMessages>> {
+    if ignored.is_empty() {
+        return None;
+    }
+
+    let label = if expanded {
+        format!("Hide Ignored Devices ({})", ignored.len())
+    } else {
+        format!("Show Ignored Devices ({})", ignored.len())
+    };
+    let toggle = button(text(label).size(14)).on_press(PageMessages::ToggleIgnoredDevices);
+
+    let mut note = column![toggle].spacing(5);
+    if expanded {
+        let reasons = ignored.iter().map(|reason| text(reason).size(14).into());
+        note = note.push(scrollable(column(reasons.collect())).height(50));
     }
+    Some(note.into())
+}
+
+// Where to find the details when something goes wrong, so they can be attached to a bug report.
+fn log_note<'a>() -> Option<Element<'a, PageMessages>> {
+    let path = log_path()?;
+    let message = format!("Details were saved to: {}", path.to_string_lossy());
+    Some(text(message).size(12).into())
+}
 
-    match version.1.cmp(&comparison.1) {
-        Ordering::Greater => return true,
-        Ordering::Less => return false,
-        Ordering::Equal => {}
+fn change_description(
+    device: &Option<Device>,
+    firmware: &Option<FirmwareDetails>,
+) -> Option<String> {
+    match (device, firmware) {
+        (Some(device), Some(firmware)) => {
+            let change = firmware.version.cmp_to(&device.version);
+            Some(change.describe(device.version, firmware.version))
+        }
+        _ => None,
     }
+}
+
+fn save_log_button<'a>() -> Element<'a, PageMessages> {
+    button(tr("update.save-logs"))
+        .on_press(PageMessages::SaveUpdateLog)
+        .into()
+}
 
-    match version.2.cmp(&comparison.2) {
-        Ordering::Greater => return true,
-        Ordering::Less => return false,
-        Ordering::Equal => {}
+fn percent_bar<'a>(percent: u8) -> Element<'a, PageMessages> {
+    let progress_bar = progress_bar(0.0..=100.0, percent as f32).width(Length::Fill);
+    let progress_text = container(text(format!("{}%", percent)))
+        .width(50)
+        .padding(Padding {
+            top: 5.0,
+            right: 0.0,
+            bottom: 0.0,
+            left: 5.0,
+        });
+    row![progress_bar, progress_text].into()
+}
+
+// This is purely for display, the original path is always what gets passed around, so a name
+// that isn't valid UTF-8 may look a little odd here, but will still be loaded correctly.
+fn display_file_name(path: &Path) -> String {
+    match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => path.to_string_lossy().to_string(),
     }
+}
 
-    if version.3 >= comparison.3 {
-        return true;
+// The end of a path is the part that tells files apart, so long paths lose their start.
+fn display_path(path: &Path, max_length: usize) -> String {
+    let path = path.to_string_lossy();
+    let length = path.chars().count();
+    if length <= max_length {
+        return path.to_string();
     }
+    let tail: String = path.chars().skip(length + 3 - max_length).collect();
+    format!("...{}", tail)
+}
 
-    false
+fn display_size(size: u64) -> String {
+    format!("{:.1} MB", size as f64 / (1024. * 1024.))
 }
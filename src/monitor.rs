@@ -0,0 +1,115 @@
+use crate::outcome::Outcome;
+use crate::{Message, PageMessages, StepMessages};
+use log::{info, warn};
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+// A client which stops reading shouldn't be able to hold up the update.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// For anyone driving updates from their own tooling, the update's progress is also published as
+// newline-delimited JSON to anything connected to this port on localhost, for example:
+//   {"event":"stage","value":"Uploading Firmware"}
+//   {"event":"percent","value":42}
+// The messages are passed on untouched, so whoever was reading the receiver carries on as before.
+pub fn monitor_progress(
+    receiver: UnboundedReceiver<Message>,
+    port: Option<u16>,
+) -> UnboundedReceiver<Message> {
+    let port = match port {
+        Some(port) => port,
+        None => return receiver,
+    };
+
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(error) => {
+            warn!("Unable to publish progress on port {}: {}", port, error);
+            return receiver;
+        }
+    };
+    info!("Publishing update progress on 127.0.0.1:{}", port);
+
+    let clients = Arc::new(Mutex::new(Vec::new()));
+    let accepted = clients.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+            accepted.lock().unwrap().push(stream);
+        }
+    });
+
+    let (sender, forwarded) = mpsc::unbounded_channel();
+    let mut receiver = receiver;
+    thread::spawn(move || {
+        while let Some(message) = receiver.blocking_recv() {
+            if let Some(event) = progress_event(&message) {
+                publish(&clients, &event);
+            }
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    forwarded
+}
+
+fn publish(clients: &Mutex<Vec<TcpStream>>, event: &str) {
+    let line = format!("{}\n", event);
+
+    // Anyone who's gone away gets dropped.
+    let mut clients = clients.lock().unwrap();
+    clients.retain_mut(|client| client.write_all(line.as_bytes()).is_ok());
+}
+
+fn progress_event(message: &Message) -> Option<String> {
+    let (event, value) = match message {
+        Message::PageMessage(message) => match message {
+            PageMessages::UpdateFirmwareStage(stage) => ("stage", json_string(stage)),
+            PageMessages::UpdateFirmwareRetry(Some(retry)) => ("retry", json_string(retry)),
+            PageMessages::UpdateFirmwarePercent(percent) => ("percent", percent.to_string()),
+            PageMessages::UpdateFirmwareOverallPercent(percent) => ("overall", percent.to_string()),
+            PageMessages::UpdateFirmwareMessage(message) => ("message", json_string(message)),
+            PageMessages::UpdateFirmwareIsError(value) => ("error", value.to_string()),
+            PageMessages::UpdateFirmwareAwaitingReboot(value) => {
+                ("awaiting_reboot", value.to_string())
+            }
+            PageMessages::UpdateFirmwareWriteIncomplete(value) => {
+                ("write_incomplete", value.to_string())
+            }
+            PageMessages::UpdateFirmwareDisconnected(value) => ("disconnected", value.to_string()),
+            PageMessages::UpdateFirmwareComplete(value) => ("complete", value.to_string()),
+            _ => return None,
+        },
+        Message::StepsMessage(StepMessages::UpdateFinished(outcome)) => {
+            ("finished", (*outcome == Outcome::Success).to_string())
+        }
+        _ => return None,
+    };
+    Some(format!("{{\"event\":\"{}\",\"value\":{}}}", event, value))
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            character if character.is_control() => {
+                escaped.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            character => escaped.push(character),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
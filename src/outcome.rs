@@ -0,0 +1,26 @@
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// How the run ended, returned as the process exit code so anything wrapping the updater can tell
+// whether the firmware was actually flashed. The last thing to happen wins, so picking a bad file
+// and then a good one doesn't leave a stale failure behind.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Outcome {
+    Success = 0,
+    Incomplete = 1,
+    DeviceNotFound = 2,
+    InvalidFirmware = 3,
+    DownloadFailed = 4,
+    UpdateFailed = 5,
+    SetupFailed = 6,
+}
+
+static OUTCOME: AtomicU8 = AtomicU8::new(Outcome::Incomplete as u8);
+
+pub fn set_outcome(outcome: Outcome) {
+    OUTCOME.store(outcome as u8, Ordering::Relaxed);
+}
+
+pub fn exit_code() -> ExitCode {
+    ExitCode::from(OUTCOME.load(Ordering::Relaxed))
+}
@@ -0,0 +1,83 @@
+use crate::config::{parse_device, read_config_lines};
+use crate::firmware::VersionNumber;
+use crate::DeviceType;
+use log::warn;
+use std::sync::OnceLock;
+
+// Firmware releases known to cause problems (Device, Version, Reason), these are refused unless
+// the user explicitly overrides the warning.
+const BLOCKED_FIRMWARE: &[(DeviceType, VersionNumber, &str)] = &[];
+
+// Extra rules can be added locally, one per line, in the form:
+//   block <full|mini|any> <version> <reason>
+//   allow <full|mini|any> <version>
+// If any 'allow' rules are present, only those versions can be flashed.
+const POLICY_FILE: &str = "firmware-policy.txt";
+
+static RULES: OnceLock<PolicyRules> = OnceLock::new();
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FirmwarePolicy {
+    Allowed,
+    Blocked(String),
+    NotApproved,
+}
+
+struct PolicyRules {
+    blocked: Vec<(Option<DeviceType>, VersionNumber, String)>,
+    allowed: Vec<(Option<DeviceType>, VersionNumber)>,
+}
+
+pub fn check_policy(device_type: DeviceType, version: VersionNumber) -> FirmwarePolicy {
+    let rules = RULES.get_or_init(load_rules);
+    let matches = |rule_device: &Option<DeviceType>, rule_version: &VersionNumber| {
+        (rule_device.is_none() || *rule_device == Some(device_type)) && *rule_version == version
+    };
+
+    if !rules.allowed.is_empty() && !rules.allowed.iter().any(|(d, v)| matches(d, v)) {
+        return FirmwarePolicy::NotApproved;
+    }
+
+    if let Some((_, _, reason)) = rules.blocked.iter().find(|(d, v, _)| matches(d, v)) {
+        return FirmwarePolicy::Blocked(reason.clone());
+    }
+
+    FirmwarePolicy::Allowed
+}
+
+fn load_rules() -> PolicyRules {
+    let mut rules = PolicyRules {
+        blocked: BLOCKED_FIRMWARE
+            .iter()
+            .map(|(device, version, reason)| (Some(*device), *version, reason.to_string()))
+            .collect(),
+        allowed: vec![],
+    };
+
+    for line in read_config_lines(POLICY_FILE) {
+        if let Err(error) = parse_rule(&line, &mut rules) {
+            warn!("Ignoring Policy Rule '{}': {}", line, error);
+        }
+    }
+    rules
+}
+
+fn parse_rule(line: &str, rules: &mut PolicyRules) -> Result<(), String> {
+    let mut parts = line.splitn(4, char::is_whitespace);
+    let (kind, device, version) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(kind), Some(device), Some(version)) => (kind, device, version),
+        _ => return Err(String::from("Expected <block|allow> <device> <version>")),
+    };
+
+    let device = parse_device(device)?;
+    let version = version.parse::<VersionNumber>()?;
+    match kind.to_lowercase().as_str() {
+        "block" => {
+            let reason = parts.next().unwrap_or("No reason given").trim();
+            rules.blocked.push((device, version, reason.to_string()));
+        }
+        "allow" => rules.allowed.push((device, version)),
+        _ => return Err(format!("Unknown Rule: {}", kind)),
+    }
+    Ok(())
+}
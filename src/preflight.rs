@@ -1,7 +1,8 @@
 use crate::{Message, PageMessages};
+use log::{info, warn};
 use std::thread::sleep;
 use std::time::Duration;
-use sysinfo::{ProcessRefreshKind, RefreshKind, System, UpdateKind};
+use sysinfo::{ProcessRefreshKind, RefreshKind, Signal, System, UpdateKind};
 use tokio::sync::mpsc::UnboundedSender;
 
 
@@ -10,35 +11,61 @@ const BETA: &str = "GoXLR Beta App.exe";
 const UTIL: &str = "goxlr-daemon.exe";
 const UTIL_LINUX: &str = "goxlr-daemon";
 
+// On macOS the daemon keeps its name, but it can also be running inside the app bundle.
+const UTIL_MAC: &str = "goxlr-daemon";
+const UTIL_MAC_BUNDLE: &str = "GoXLR Utility";
+
+// The names sysinfo will report for each of the things which need to be closed on this platform.
+// The official apps only exist on Windows.
+struct ProcessNames {
+    app: &'static [&'static str],
+    beta: &'static [&'static str],
+    util: &'static [&'static str],
+}
+
+fn process_names() -> ProcessNames {
+    if cfg!(windows) {
+        ProcessNames {
+            app: &[APP],
+            beta: &[BETA],
+            util: &[UTIL],
+        }
+    } else if cfg!(target_os = "macos") {
+        ProcessNames {
+            app: &[],
+            beta: &[],
+            util: &[UTIL_MAC, UTIL_MAC_BUNDLE],
+        }
+    } else {
+        ProcessNames {
+            app: &[],
+            beta: &[],
+            util: &[UTIL_LINUX],
+        }
+    }
+}
+
+fn is_running(system: &System, names: &[&str]) -> bool {
+    names
+        .iter()
+        .any(|name| system.processes_by_exact_name(name).count() > 0)
+}
+
+const CLOSE_TIMEOUT_SECS: u32 = 5;
+
 pub fn status_check(sender: UnboundedSender<Message>) {
-    println!("Starting Task Checker..");
+    info!("Starting Task Checker..");
 
     let kind = ProcessRefreshKind::new().with_user(UpdateKind::Always);
     let refresh_kind = RefreshKind::new().with_processes(kind);
     let mut system = System::new_with_specifics(refresh_kind);
+    let names = process_names();
 
     loop {
-        let mut app_running = false;
-        let mut beta_running = false;
-        let mut utility_running = false;
-
-
         system.refresh_processes();
-        if system.processes_by_exact_name(APP).count() > 0 {
-            app_running = true;
-        }
-
-        if system.processes_by_exact_name(BETA).count() > 0 {
-            beta_running = true;
-        }
-
-        if system.processes_by_exact_name(UTIL).count() > 0 {
-            utility_running = true;
-        }
-
-        if system.processes_by_exact_name(UTIL_LINUX).count() > 0 {
-            utility_running = true;
-        }
+        let app_running = is_running(&system, names.app);
+        let beta_running = is_running(&system, names.beta);
+        let utility_running = is_running(&system, names.util);
 
         // Fire off the message..
         let _ = sender.send(Message::PageMessage(PageMessages::UpdateStatusCheck(
@@ -52,5 +79,173 @@ pub fn status_check(sender: UnboundedSender<Message>) {
         }
         sleep(Duration::from_secs(1));
     }
-    println!("Task Checker Terminated");
+    info!("Task Checker Terminated");
+}
+
+// Closes anything status_check would complain about. The Utility gets a chance to shut down
+// cleanly first, the official apps don't respond to that so are just killed.
+pub fn close_apps() {
+    let kind = ProcessRefreshKind::new();
+    let mut system = System::new_with_specifics(RefreshKind::new().with_processes(kind));
+    let names = process_names();
+
+    for name in names.util {
+        for process in system.processes_by_exact_name(name) {
+            if process.kill_with(Signal::Term).is_none() {
+                process.kill();
+            }
+        }
+    }
+
+    for name in names.app.iter().chain(names.beta) {
+        for process in system.processes_by_exact_name(name) {
+            process.kill();
+        }
+    }
+
+    // Give the Utility a moment to save and exit, then make sure it's actually gone.
+    for _ in 0..CLOSE_TIMEOUT_SECS {
+        sleep(Duration::from_secs(1));
+        system.refresh_processes();
+        if !is_running(&system, names.util) {
+            return;
+        }
+    }
+
+    warn!("GoXLR Utility didn't close, killing it..");
+    for name in names.util {
+        for process in system.processes_by_exact_name(name) {
+            process.kill();
+        }
+    }
+}
+
+// We can't ask the App itself (it should be closed by now), so look at what's installed instead.
+#[cfg(windows)]
+pub fn installed_app_version() -> Option<String> {
+    use winreg::enums::HKEY_LOCAL_MACHINE;
+    use winreg::RegKey;
+
+    let uninstall_keys = [
+        "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall",
+        "SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall",
+    ];
+
+    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+    for path in uninstall_keys {
+        if let Ok(uninstall) = hklm.open_subkey(path) {
+            for name in uninstall.enum_keys().flatten() {
+                if let Ok(key) = uninstall.open_subkey(name) {
+                    let display_name: String = key.get_value("DisplayName").unwrap_or_default();
+                    if display_name.starts_with("GoXLR") && display_name.contains("App") {
+                        if let Ok(version) = key.get_value::<String, _>("DisplayVersion") {
+                            return Some(version);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+// The official App is Windows only.
+#[cfg(not(windows))]
+pub fn installed_app_version() -> Option<String> {
+    None
+}
+
+// Windows' Fast Startup hibernates the kernel rather than shutting down, so USB devices aren't
+// fully reset on the next boot. We can't easily tell how this boot happened, but if it's enabled
+// there's a good chance it was used.
+#[cfg(windows)]
+pub fn fast_startup_enabled() -> bool {
+    use winreg::enums::HKEY_LOCAL_MACHINE;
+    use winreg::RegKey;
+
+    let path = "SYSTEM\\CurrentControlSet\\Control\\Session Manager\\Power";
+    RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey(path)
+        .and_then(|key| key.get_value::<u32, _>("HiberbootEnabled"))
+        .map(|value| value == 1)
+        .unwrap_or(false)
+}
+
+#[cfg(not(windows))]
+pub fn fast_startup_enabled() -> bool {
+    false
+}
+
+// The crate version alone doesn't say which build someone's running, so include the commit.
+pub fn build_version() -> String {
+    format!(
+        "{} (commit {}, built {})",
+        env!("CARGO_PKG_VERSION"),
+        env!("BUILD_COMMIT"),
+        env!("BUILD_DATE")
+    )
+}
+
+// Everything we'd want to know about where the updater is running when looking at a bug report.
+pub fn log_environment() {
+    info!("GoXLR Firmware Updater {}", build_version());
+    info!("goxlr-usb: {}", env!("GOXLR_USB_VERSION"));
+    info!(
+        "OS: {} (Kernel {})",
+        System::long_os_version().unwrap_or_else(|| std::env::consts::OS.to_string()),
+        System::kernel_version().unwrap_or_else(|| String::from("Unknown"))
+    );
+    info!("Architecture: {}", std::env::consts::ARCH);
+    info!("Elevated: {}", is_elevated());
+
+    for (key, value) in std::env::vars().filter(|(key, _)| key.starts_with("GOXLR_")) {
+        info!("Environment: {}={}", key, value);
+    }
+}
+
+#[cfg(windows)]
+fn is_elevated() -> bool {
+    unsafe { windows::Win32::UI::Shell::IsUserAnAdmin().as_bool() }
+}
+
+#[cfg(unix)]
+fn is_elevated() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(any(windows, unix)))]
+fn is_elevated() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(windows)]
+    fn names_on_windows() {
+        let names = process_names();
+        assert_eq!(names.app, ["GoXLR App.exe"]);
+        assert_eq!(names.beta, ["GoXLR Beta App.exe"]);
+        assert_eq!(names.util, ["goxlr-daemon.exe"]);
+    }
+
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn names_on_macos() {
+        let names = process_names();
+        assert!(names.app.is_empty());
+        assert!(names.beta.is_empty());
+        assert_eq!(names.util, ["goxlr-daemon", "GoXLR Utility"]);
+    }
+
+    #[test]
+    #[cfg(not(any(windows, target_os = "macos")))]
+    fn names_on_linux() {
+        let names = process_names();
+        assert!(names.app.is_empty());
+        assert!(names.beta.is_empty());
+        assert_eq!(names.util, ["goxlr-daemon"]);
+    }
 }
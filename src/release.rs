@@ -0,0 +1,97 @@
+use crate::{Message, PageMessages};
+use log::{debug, info, warn};
+use reqwest::blocking::Client;
+use std::process::Command;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/FrostyCoolSlug/goxlr-firmware-updater/releases/latest";
+const RELEASE_PAGE_URL: &str =
+    "https://github.com/FrostyCoolSlug/goxlr-firmware-updater/releases/tag/";
+
+// This is only advisory, it's not worth holding anything up for.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Where firmware comes from changes over time, so an old copy of the updater can end up looking
+// in the wrong place. If there's a newer release, let the user know about it.
+pub fn check_for_release(sender: UnboundedSender<Message>) {
+    let tag = match latest_release_tag() {
+        Ok(tag) => tag,
+        Err(error) => {
+            warn!("Unable to check for a newer updater: {}", error);
+            return;
+        }
+    };
+
+    let current = env!("CARGO_PKG_VERSION");
+    match (parse_version(&tag), parse_version(current)) {
+        (Some(latest), Some(running)) if latest > running => {
+            info!(
+                "A newer updater is available: {} (running {})",
+                tag, current
+            );
+            let message = PageMessages::NewerReleaseAvailable(tag);
+            let _ = sender.send(Message::PageMessage(message));
+        }
+        (Some(_), Some(_)) => debug!("Updater is up to date ({})", current),
+        _ => warn!("Unable to compare release {} with {}", tag, current),
+    }
+}
+
+pub fn release_page(tag: &str) -> String {
+    format!("{}{}", RELEASE_PAGE_URL, tag)
+}
+
+pub fn open_release_page(tag: &str) {
+    let opener = if cfg!(windows) {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+
+    if let Err(error) = Command::new(opener).arg(release_page(tag)).spawn() {
+        warn!("Unable to open the release page: {}", error);
+    }
+}
+
+fn latest_release_tag() -> Result<String, String> {
+    // GitHub turns away API requests without a User-Agent.
+    let client = Client::builder()
+        .timeout(CHECK_TIMEOUT)
+        .user_agent(concat!(
+            "goxlr-firmware-updater/",
+            env!("CARGO_PKG_VERSION")
+        ))
+        .build()
+        .map_err(|error| error.to_string())?;
+
+    let response = client
+        .get(LATEST_RELEASE_URL)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .map_err(|error| error.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Server responded with {}", response.status()));
+    }
+
+    let body = response.text().map_err(|error| error.to_string())?;
+    string_field(&body, "tag_name").ok_or_else(|| String::from("No tag_name in the response"))
+}
+
+// The tag is all we need out of the response, so rather than pull in a JSON parser for one
+// field, find it directly. Tags don't contain quotes, so there's no escaping to worry about.
+fn string_field(body: &str, name: &str) -> Option<String> {
+    let key = format!("\"{}\"", name);
+    let rest = body[body.find(&key)? + key.len()..].trim_start();
+    let rest = rest.strip_prefix(':')?.trim_start().strip_prefix('"')?;
+    rest.find('"').map(|end| rest[..end].to_string())
+}
+
+// Tags are in the form v1.2.3, anything that isn't is ignored rather than guessed at.
+fn parse_version(version: &str) -> Option<Vec<u32>> {
+    let version = version.trim().trim_start_matches('v');
+    version.split('.').map(|part| part.parse().ok()).collect()
+}
@@ -0,0 +1,150 @@
+use iced::advanced::layout::{Limits, Node};
+use iced::advanced::widget::{Operation, Tree};
+use iced::advanced::{mouse, overlay, renderer, Clipboard, Layout, Shell, Widget};
+use iced::{event, Element, Event, Length, Rectangle, Renderer};
+
+// A scrollable only reports its position once it's been scrolled, and content which fits without
+// scrolling can't be. This wraps one, and sends a message whenever its content turns out to fit,
+// so anything waiting on the user reaching the end isn't left waiting forever.
+pub struct FitCheck<'a, Message> {
+    scrollable: Element<'a, Message>,
+    on_fit: Option<Message>,
+}
+
+impl<'a, Message> FitCheck<'a, Message> {
+    // Nothing is sent if on_fit is None, so once the message has been handled, the caller can
+    // stop it from being sent on every redraw.
+    pub fn new(scrollable: impl Into<Element<'a, Message>>, on_fit: Option<Message>) -> Self {
+        Self {
+            scrollable: scrollable.into(),
+            on_fit,
+        }
+    }
+}
+
+impl<'a, Message: Clone> Widget<Message, Renderer> for FitCheck<'a, Message> {
+    fn width(&self) -> Length {
+        self.scrollable.as_widget().width()
+    }
+
+    fn height(&self) -> Length {
+        self.scrollable.as_widget().height()
+    }
+
+    fn layout(&self, renderer: &Renderer, limits: &Limits) -> Node {
+        let node = self.scrollable.as_widget().layout(renderer, limits);
+        Node::with_children(node.size(), vec![node])
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &iced::Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.scrollable.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            layout.children().next().unwrap(),
+            cursor,
+            viewport,
+        );
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.scrollable)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(std::slice::from_ref(&self.scrollable));
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn Operation<Message>,
+    ) {
+        self.scrollable.as_widget().operate(
+            &mut tree.children[0],
+            layout.children().next().unwrap(),
+            renderer,
+            operation,
+        );
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        let scrollable = layout.children().next().unwrap();
+
+        // Every redraw comes through here, including the first, and after the window's resized.
+        if let (Some(message), Some(content)) = (&self.on_fit, scrollable.children().next()) {
+            if content.bounds().height <= scrollable.bounds().height {
+                shell.publish(message.clone());
+            }
+        }
+
+        self.scrollable.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            scrollable,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.scrollable.as_widget().mouse_interaction(
+            &tree.children[0],
+            layout.children().next().unwrap(),
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+    ) -> Option<overlay::Element<'b, Message, Renderer>> {
+        self.scrollable.as_widget_mut().overlay(
+            &mut tree.children[0],
+            layout.children().next().unwrap(),
+            renderer,
+        )
+    }
+}
+
+impl<'a, Message: Clone + 'a> From<FitCheck<'a, Message>> for Element<'a, Message> {
+    fn from(check: FitCheck<'a, Message>) -> Self {
+        Element::new(check)
+    }
+}
@@ -0,0 +1,49 @@
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TaskbarProgress {
+    None,
+    Normal(u8),
+    Paused,
+    Error,
+}
+
+// Mirrors the current progress onto the window's taskbar button, so it can be watched without
+// having the updater focused.
+#[cfg(windows)]
+pub fn set_taskbar_progress(progress: TaskbarProgress) {
+    use windows::core::HSTRING;
+    use windows::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED,
+    };
+    use windows::Win32::UI::Shell::{
+        ITaskbarList3, TaskbarList, TBPF_ERROR, TBPF_NOPROGRESS, TBPF_NORMAL, TBPF_PAUSED,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::FindWindowW;
+
+    let title = HSTRING::from(crate::WINDOW_TITLE);
+
+    // This is purely cosmetic, so if any of it fails we just carry on without it.
+    let _ = unsafe {
+        // The window may have already initialised COM on this thread, which is fine.
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+
+        let taskbar: windows::core::Result<ITaskbarList3> =
+            CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER);
+        taskbar.and_then(|taskbar| {
+            taskbar.HrInit()?;
+            let window = FindWindowW(None, &title)?;
+
+            match progress {
+                TaskbarProgress::None => taskbar.SetProgressState(window, TBPF_NOPROGRESS),
+                TaskbarProgress::Normal(percent) => {
+                    taskbar.SetProgressState(window, TBPF_NORMAL)?;
+                    taskbar.SetProgressValue(window, percent as u64, 100)
+                }
+                TaskbarProgress::Paused => taskbar.SetProgressState(window, TBPF_PAUSED),
+                TaskbarProgress::Error => taskbar.SetProgressState(window, TBPF_ERROR),
+            }
+        })
+    };
+}
+
+#[cfg(not(windows))]
+pub fn set_taskbar_progress(_progress: TaskbarProgress) {}
@@ -0,0 +1,81 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+// The rate is averaged over the last few seconds, so a brief stall (or burst) doesn't send the
+// estimate wildly up and down.
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+// Don't guess at a rate until we've got at least this much to go on.
+const MIN_SAMPLE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TransferStats {
+    pub bytes_per_second: u64,
+    pub remaining: Duration,
+}
+
+impl Display for TransferStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let rate = self.bytes_per_second as f64;
+        let rate = if rate >= 1_000_000. {
+            format!("{:.1} MB/s", rate / 1_000_000.)
+        } else {
+            format!("{:.0} KB/s", rate / 1_000.)
+        };
+
+        let seconds = self.remaining.as_secs();
+        if seconds >= 60 {
+            write!(f, "{} – ~{}m {}s left", rate, seconds / 60, seconds % 60)
+        } else {
+            write!(f, "{} – ~{}s left", rate, seconds)
+        }
+    }
+}
+
+pub struct TransferRate {
+    total: u64,
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl TransferRate {
+    pub fn new(total: u64) -> Self {
+        Self::resume(total, 0)
+    }
+
+    // For picking up part way through, so what's already there isn't counted towards the rate.
+    pub fn resume(total: u64, transferred: u64) -> Self {
+        let mut samples = VecDeque::new();
+        samples.push_back((Instant::now(), transferred));
+        Self { total, samples }
+    }
+
+    // Records how much has been transferred so far, and returns the current rate once there's
+    // enough history to work one out.
+    pub fn update(&mut self, transferred: u64) -> Option<TransferStats> {
+        let now = Instant::now();
+        self.samples.push_back((now, transferred));
+        while self.samples.len() > 2 {
+            match self.samples.get(1) {
+                Some((time, _)) if now.duration_since(*time) >= RATE_WINDOW => {
+                    self.samples.pop_front();
+                }
+                _ => break,
+            }
+        }
+
+        let (start, start_bytes) = *self.samples.front()?;
+        let elapsed = now.duration_since(start);
+        if elapsed < MIN_SAMPLE || transferred <= start_bytes {
+            return None;
+        }
+
+        let rate = (transferred - start_bytes) as f64 / elapsed.as_secs_f64();
+        let remaining = self.total.saturating_sub(transferred) as f64 / rate;
+        Some(TransferStats {
+            bytes_per_second: rate as u64,
+            remaining: Duration::from_secs_f64(remaining),
+        })
+    }
+}